@@ -29,6 +29,44 @@ pub enum MajorBody {
     Pluto
 }
 
+impl MajorBody {
+    pub const ALL: [MajorBody; 7] = [
+        MajorBody::Earth,
+        MajorBody::Mars,
+        MajorBody::Jupiter,
+        MajorBody::Saturn,
+        MajorBody::Uranus,
+        MajorBody::Neptune,
+        MajorBody::Pluto,
+    ];
+
+    // equatorial radius in km
+    pub fn radius(&self) -> f64 {
+        match self {
+            MajorBody::Earth => 6378.137,
+            MajorBody::Mars => 3396.19,
+            MajorBody::Jupiter => 71492.0,
+            MajorBody::Saturn => 60268.0,
+            MajorBody::Uranus => 25559.0,
+            MajorBody::Neptune => 24764.0,
+            MajorBody::Pluto => 1188.3,
+        }
+    }
+
+    // GM in km^3/s^2
+    pub fn gm(&self) -> f64 {
+        match self {
+            MajorBody::Earth => 398600.435,
+            MajorBody::Mars => 42828.375,
+            MajorBody::Jupiter => 126686531.9,
+            MajorBody::Saturn => 37931206.2,
+            MajorBody::Uranus => 5793951.3,
+            MajorBody::Neptune => 6835099.5,
+            MajorBody::Pluto => 869.6,
+        }
+    }
+}
+
 impl FromStr for MajorBody {
     type Err = UnknownPlanetError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -1,13 +1,15 @@
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
-use druid::kurbo::{Line, Rect};
-use druid::piet::kurbo::Shape;
+use druid::kurbo::{BezPath, Line, Rect};
 use druid::piet::Color;
-use druid::piet::TextLayoutBuilder;
+use druid::piet::{TextLayout, TextLayoutBuilder};
 use druid::RenderContext;
 use druid::{
     kurbo::Ellipse, piet::Text, widget::ListIter, BoxConstraints, Data, Env, Event, EventCtx,
-    LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Size, TimerToken, UpdateCtx, Widget,
+    KbKey, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Selector, Size, TimerToken,
+    UpdateCtx, Widget,
 };
 use evalexpr::*;
 use satellite_data::{
@@ -15,6 +17,22 @@ use satellite_data::{
     satellites::{MajorBody, Satellite},
 };
 
+use crate::axis::{linear_ticks, AxisScale};
+use crate::color_map::{major_body_color, ColorMapping, Colormap};
+use crate::filter_builder::{Condition, Join};
+use crate::histogram::{self, Histogram, HistogramStyle};
+use crate::library::SavedQuery;
+use crate::panels::Panel;
+use crate::picking::{closest, OrbitShape, ORBIT_TOLERANCE, POINT_TOLERANCE};
+use crate::query::Query;
+use crate::selection::{
+    apply, contains, orbit_touches, polygon_bounds, Gesture, SelectionMode, SelectionTool,
+};
+use crate::spatial_index::SpatialIndex;
+use crate::system::{extent, layout_tiles, SystemView, Tile};
+
+pub const AUTO_FIT: Selector = Selector::new("astronomy_canvas.auto_fit");
+
 #[derive(Clone, Debug)]
 pub struct SatelliteteVec(pub Vec<SatelliteWrapper>);
 
@@ -64,40 +82,122 @@ impl Data for SatelliteWrapper {
     }
 }
 pub struct AstronomyCanvas {
-    pub selected_satellites: Vec<Satellite>,
+    pub gesture: Option<Gesture>,
     pub selected_satellite: Option<SatelliteWrapper>,
     pub move_bul: bool,
+    pub drag_point: Option<Point>,
+    pub hovered_satellite: Option<usize>,
+    pub hover_point: Option<Point>,
     pub center: Point,
     pub database: Database,
     pub full_database: Database,
     pub count_change_timer: Option<TimerToken>,
     pub selected_update_timer: Option<TimerToken>,
+    pub color_mapping: ColorMapping,
+    pub index: SpatialIndex,
+    pub index_state: Option<IndexState>,
+    pub size: Size,
+    pub tiles: Vec<Tile>,
+    pub histogram: Option<(Histogram, Rect)>,
+    pub x_query: Query,
+    pub y_query: Query,
+    pub filter_status: String,
+}
+
+// everything the screen positions in the spatial index depend on
+#[derive(Clone, PartialEq, Debug)]
+pub struct IndexState {
+    graph_view: bool,
+    system: SystemView,
+    size: Size,
+    center: Point,
+    scale: f64,
+    graph_x_scale: f64,
+    graph_y_scale: f64,
+    x_value: String,
+    y_value: String,
+    x_axis: AxisScale,
+    y_axis: AxisScale,
+}
+
+impl IndexState {
+    fn new(data: &AstronomyCanvasData, center: Point, size: Size) -> IndexState {
+        IndexState {
+            graph_view: data.graph_view,
+            system: data.system,
+            size,
+            center,
+            scale: data.scale,
+            graph_x_scale: data.graph_x_scale,
+            graph_y_scale: data.graph_y_scale,
+            x_value: data.x_value.clone(),
+            y_value: data.y_value.clone(),
+            x_axis: data.x_axis,
+            y_axis: data.y_axis,
+        }
+    }
 }
 #[derive(Clone, Data, Lens, Debug)]
 pub struct AstronomyCanvasData {
     pub all_displayed: usize,
     pub selected: bool,
     pub scale: f64,
-    pub center: Point,
+    pub graph_x_scale: f64,
+    pub graph_y_scale: f64,
     pub toggle_distance: bool,
     pub toggle_angle: bool,
     pub toggle_major_semiaxes: bool,
-    pub mouse_point: Option<Point>,
     pub selected_satellites: SatelliteteVec,
+    pub displayed_satellites: SatelliteteVec,
     pub match_string: String,
+    pub filter_status: String,
+    pub axis_status: String,
     pub selected_satellite: Option<SatelliteWrapper>,
     pub graph_view: bool,
     pub x_value: String,
     pub y_value: String,
+    pub color_by_expression: bool,
+    pub color_expression: String,
+    pub colormap: Colormap,
+    pub x_axis: AxisScale,
+    pub y_axis: AxisScale,
+    pub toggle_error_bars: bool,
+    pub histogram_view: bool,
+    pub bin_width: String,
+    pub histogram_style: HistogramStyle,
+    pub toggle_kde: bool,
+    pub selection_tool: SelectionTool,
+    pub system: SystemView,
+    pub panels: Arc<Vec<Panel>>,
+    pub queries: Arc<Vec<SavedQuery>>,
+    pub query_name: String,
+    pub library_status: String,
+    pub columns: Arc<Vec<String>>,
+    pub column_definition: String,
+    pub columns_status: String,
+    pub summary_terms: String,
+    pub group_by: String,
+    pub pair_query: String,
+    pub pairs: Arc<Vec<(usize, usize)>>,
+    pub pair_status: String,
+    pub filter_conditions: Arc<Vec<Condition>>,
+    pub filter_join: Join,
+    pub builder_status: String,
 }
 
 impl Widget<AstronomyCanvasData> for AstronomyCanvas {
     fn paint(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, env: &Env) {
+        self.ensure_index(data);
         if !data.graph_view {
             self.render_circular_view(ctx, data, env);
+        } else if data.histogram_view {
+            self.render_histogram(ctx, data);
         } else {
             self.render_graph_view(ctx, data, env);
         }
+        self.color_mapping.draw_legend(ctx, &self.database);
+        self.render_gesture(ctx, data);
+        self.render_tooltip(ctx);
     }
 
     fn layout(
@@ -112,7 +212,8 @@ impl Widget<AstronomyCanvasData> for AstronomyCanvas {
             ctx.window().get_size().height,
         );
 
-        bc.constrain(default_size)
+        self.size = bc.constrain(default_size);
+        self.size
     }
 
     fn event(
@@ -123,33 +224,50 @@ impl Widget<AstronomyCanvasData> for AstronomyCanvas {
         _env: &Env,
     ) {
         match event {
+            Event::Wheel(_) if self.histogram_view(data) => {}
             Event::Wheel(mouse_event) => {
-                data.mouse_point = None;
-
-                let (_, y) = mouse_event.wheel_delta.into();
-
-                if y.is_sign_positive() {
-                    data.scale *= 2.0
+                let (x, y) = mouse_event.wheel_delta.into();
+                let delta = if y != 0.0 { y } else { x };
+                let factor = 2f64.powf(delta / 240.0);
+                let mods = mouse_event.mods;
+                let x_factor = if mods.shift() && data.graph_view {
+                    1.0
                 } else {
-                    data.scale /= 2.0
-                }
+                    factor
+                };
+                let y_factor = if mods.ctrl() && data.graph_view {
+                    1.0
+                } else {
+                    factor
+                };
+                self.zoom(data, mouse_event.pos, x_factor, y_factor);
                 ctx.request_paint();
-                ctx.request_layout();
             }
             Event::MouseDown(mouse_event) => {
+                ctx.request_focus();
                 match mouse_event.button {
                     druid::MouseButton::Left => {
-                        data.mouse_point = None;
-
-                        let mouse_pos = mouse_event.pos;
-                        data.center = mouse_pos;
+                        self.drag_point = Some(mouse_event.pos);
                         ctx.set_active(true);
                     }
-                    druid::MouseButton::Right => {
-                        let mouse_pos = mouse_event.pos;
-                        data.mouse_point = Some(mouse_pos);
-                        self.selected_satellites.clear();
-                    }
+                    druid::MouseButton::Right if self.histogram_view(data) => {}
+                    druid::MouseButton::Right => match self.gesture.as_mut() {
+                        Some(gesture) if gesture.tool == SelectionTool::Polygon => {
+                            if mouse_event.count >= 2 {
+                                self.finish_gesture(data);
+                            } else {
+                                gesture.add_vertex(mouse_event.pos);
+                            }
+                        }
+                        _ => {
+                            let mode = SelectionMode::from_modifiers(mouse_event.mods);
+                            self.gesture =
+                                Some(Gesture::new(mouse_event.pos, data.selection_tool, mode));
+                            if data.selection_tool != SelectionTool::Polygon {
+                                ctx.set_active(true);
+                            }
+                        }
+                    },
                     druid::MouseButton::Middle => {
                         data.selected = !data.selected
                         // data.mouse_point = None;
@@ -161,30 +279,114 @@ impl Widget<AstronomyCanvasData> for AstronomyCanvas {
                 ctx.request_layout();
             }
             Event::MouseMove(mouse_event) => {
-                if ctx.is_active() {
-                    self.move_bul = true;
-                    let mouse_pos = mouse_event.pos;
-                    data.center = mouse_pos;
+                if let Some(gesture) = self.gesture.as_mut() {
+                    gesture.drag_to(mouse_event.pos);
+                    ctx.request_paint();
+                } else if let (true, Some(drag_point)) = (ctx.is_active(), self.drag_point) {
+                    if self.move_bul || drag_point.distance(mouse_event.pos) > 3.0 {
+                        self.move_bul = true;
+                        self.center += mouse_event.pos - drag_point;
+                        self.drag_point = Some(mouse_event.pos);
+                        ctx.request_paint();
+                    }
+                } else {
+                    self.ensure_index(data);
+                    let hovered = self.pick(data, mouse_event.pos);
+                    if hovered.is_some() || self.hovered_satellite.is_some() {
+                        ctx.request_paint();
+                    }
+                    self.hovered_satellite = hovered;
+                    self.hover_point = Some(mouse_event.pos);
+                }
+            }
+            Event::KeyDown(key_event) => {
+                let pan = 50.0;
+                let step = 1.25;
+                let (x_factor, y_factor) = if !data.graph_view {
+                    (step, step)
+                } else if key_event.mods.ctrl() {
+                    (step, 1.0)
+                } else if key_event.mods.alt() {
+                    (1.0, step)
+                } else {
+                    (step, step)
+                };
+                let size = ctx.size();
+                let middle = Point::new(size.width / 2.0, size.height / 2.0);
+                match &key_event.key {
+                    KbKey::ArrowLeft => self.center.x += pan,
+                    KbKey::ArrowRight => self.center.x -= pan,
+                    KbKey::ArrowUp => self.center.y += pan,
+                    KbKey::ArrowDown => self.center.y -= pan,
+                    KbKey::Character(key) if key == "+" || key == "=" => {
+                        self.zoom(data, middle, 1.0 / x_factor, 1.0 / y_factor)
+                    }
+                    KbKey::Character(key) if key == "-" || key == "_" => {
+                        self.zoom(data, middle, x_factor, y_factor)
+                    }
+                    KbKey::Character(key) if key == "0" => self.auto_fit(size, data),
+                    KbKey::Enter if self.gesture.is_some() => self.finish_gesture(data),
+                    KbKey::Escape if self.gesture.is_some() => self.gesture = None,
+                    _ => return,
                 }
+                ctx.set_handled();
+                ctx.request_paint();
             }
-            Event::MouseUp(_mouse_event) => {
+            Event::MouseUp(mouse_event) => {
                 ctx.set_active(false);
-                let satellites: Vec<SatelliteWrapper> = self
-                    .selected_satellites
-                    .clone()
-                    .into_iter()
-                    .map(|satellite| SatelliteWrapper {
-                        satellite: satellite.id,
-                        selected: false,
-                    })
-                    .collect();
-                data.selected_satellites = SatelliteteVec(satellites);
+                let mode = SelectionMode::from_modifiers(mouse_event.mods);
+                match mouse_event.button {
+                    druid::MouseButton::Left if !self.move_bul && self.histogram_view(data) => {
+                        let hits = self.histogram_bin(mouse_event.pos).unwrap_or_default();
+                        select(data, &hits, mode);
+                    }
+                    druid::MouseButton::Left if !self.move_bul => {
+                        self.ensure_index(data);
+                        let picked = self.pick(data, mouse_event.pos);
+                        if picked.is_some() || mode == SelectionMode::Replace {
+                            self.selected_satellite = picked.map(|satellite| SatelliteWrapper {
+                                satellite,
+                                selected: true,
+                            });
+                        }
+                        let hits: Vec<usize> = picked.into_iter().collect();
+                        select(data, &hits, mode);
+                    }
+                    druid::MouseButton::Right => {
+                        let polygon = self
+                            .gesture
+                            .as_ref()
+                            .map_or(false, |gesture| gesture.tool == SelectionTool::Polygon);
+                        if !polygon {
+                            self.finish_gesture(data);
+                        }
+                    }
+                    _ => {}
+                }
+                ctx.request_paint();
+                self.drag_point = None;
                 self.move_bul = false;
             }
+            Event::Command(command) if command.is(AUTO_FIT) => {
+                self.auto_fit(ctx.size(), data);
+                ctx.request_paint();
+            }
             Event::Timer(_event) => {
                 ctx.request_layout();
                 ctx.request_paint();
                 data.all_displayed = self.database.data.len();
+                data.filter_status = self.filter_status.clone();
+                data.axis_status = self.axis_status(data);
+                data.displayed_satellites = SatelliteteVec(
+                    self.database
+                        .data
+                        .iter()
+                        .map(|satellite| SatelliteWrapper {
+                            satellite: satellite.id,
+                            selected: false,
+                        })
+                        .collect(),
+                );
             }
             _ => {}
         };
@@ -217,6 +419,29 @@ impl Widget<AstronomyCanvasData> for AstronomyCanvas {
                 ctx.request_timer(Duration::from_millis(1));
             }
         }
+        if !old_data.selected_satellite.same(&data.selected_satellite) {
+            self.selected_satellite = data.selected_satellite;
+            ctx.request_paint();
+        }
+        if !old_data.selected_satellites.same(&data.selected_satellites)
+            || !old_data.pairs.same(&data.pairs)
+        {
+            ctx.request_paint();
+        }
+        if data.x_axis != old_data.x_axis
+            || data.toggle_error_bars != old_data.toggle_error_bars
+            || data.y_axis != old_data.y_axis
+            || data.x_value != old_data.x_value
+            || data.y_value != old_data.y_value
+            || data.histogram_view != old_data.histogram_view
+            || data.bin_width != old_data.bin_width
+            || data.histogram_style != old_data.histogram_style
+            || data.toggle_kde != old_data.toggle_kde
+        {
+            ctx.request_paint();
+            // the timer reports problems with the new axis expressions
+            ctx.request_timer(Duration::from_millis(1));
+        }
         if data.toggle_distance != old_data.toggle_distance
             || data.toggle_angle != old_data.toggle_angle
             || data.toggle_major_semiaxes != old_data.toggle_major_semiaxes
@@ -224,97 +449,420 @@ impl Widget<AstronomyCanvasData> for AstronomyCanvas {
             ctx.request_paint();
             ctx.request_layout();
         }
-        if old_data.center != data.center {
-            if self.move_bul {
-                self.center = Point::new(
-                    self.center.x + old_data.center.x - data.center.x,
-                    self.center.y + old_data.center.y - data.center.y,
-                );
-            }
+        if data.graph_view != old_data.graph_view {
             ctx.request_paint();
-            ctx.request_layout();
+            ctx.request_timer(Duration::from_millis(1));
         }
-        if data.match_string != old_data.match_string {
-            if data.match_string.is_empty() {
-                self.database = self.full_database.clone();
-                return;
+        if data.match_string != old_data.match_string
+            || data.system != old_data.system
+            || !data.columns.same(&old_data.columns)
+        {
+            self.refilter(data);
+            if data.system != old_data.system {
+                if let SystemView::Single(_) = data.system {
+                    ctx.submit_command(AUTO_FIT);
+                }
             }
-            let database = &self.full_database;
-            let database: Vec<Satellite> = database
-                .data
-                .iter()
-                .cloned()
-                .filter(|satellite: &Satellite| {
-                    parse_logicall_expression(satellite, &data.match_string)
-                })
-                .collect();
-            let database = Database { data: database };
-            self.database = database;
-            // let all_displayed = self.database.data.len();
-            // self.all_displayed = all_displayed
             ctx.request_paint();
             ctx.request_layout();
             ctx.request_timer(Duration::from_millis(1));
         }
+        if data.color_by_expression != old_data.color_by_expression
+            || data.color_expression != old_data.color_expression
+            || data.colormap != old_data.colormap
+        {
+            self.update_color_mapping(data);
+            ctx.request_paint();
+        }
     }
     fn lifecycle(
         &mut self,
-        _ctx: &mut LifeCycleCtx,
-        _event: &LifeCycle,
-        _data: &AstronomyCanvasData,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AstronomyCanvasData,
         _env: &Env,
     ) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                ctx.register_for_focus();
+                // a linked view opens on the filter already in use
+                self.refilter(data);
+            }
+            LifeCycle::HotChanged(false) => {
+                self.hovered_satellite = None;
+                ctx.request_paint();
+            }
+            _ => {}
+        }
     }
 }
 
 impl AstronomyCanvas {
-    fn render_circular_view(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
-        let scale = data.scale * 5000.0;
+    // factors above one zoom out, the point under `anchor` stays in place
+    fn zoom(
+        &mut self,
+        data: &mut AstronomyCanvasData,
+        anchor: Point,
+        x_factor: f64,
+        y_factor: f64,
+    ) {
+        if data.graph_view {
+            data.graph_x_scale *= x_factor;
+            data.graph_y_scale *= y_factor;
+        } else {
+            data.scale *= x_factor;
+        }
+        let y_factor = if data.graph_view { y_factor } else { x_factor };
+        self.center = Point::new(
+            anchor.x - (anchor.x - self.center.x) / x_factor,
+            anchor.y - (anchor.y - self.center.y) / y_factor,
+        );
+    }
+
+    fn auto_fit(&mut self, size: Size, data: &mut AstronomyCanvasData) {
+        let margin = 0.9;
+        if !data.graph_view {
+            let extent = extent(self.database.data.iter());
+            if extent > 0.0 {
+                let radius = size.width.min(size.height) / 2.0 * margin;
+                data.scale = extent / radius / 5000.0;
+                self.center = Point::new(size.width / 2.0, size.height / 2.0);
+            }
+            return;
+        }
+
+        self.sync_queries(data);
+        let (_, x_factor) = parse_axis_expression(&data.x_value);
+        let x_factor = x_factor * data.x_axis.units_per_pixel();
+        let (_, y_factor) = parse_axis_expression(&data.y_value);
+        let y_factor = y_factor * data.y_axis.units_per_pixel();
+        let mut x_range = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut y_range = (f64::INFINITY, f64::NEG_INFINITY);
         for satellite in &self.database.data {
+            let (x, y) = match (
+                self.x_query.known_value(satellite),
+                self.y_query.known_value(satellite),
+            ) {
+                (Some(x), Some(y)) => (data.x_axis.forward(x), data.y_axis.forward(y)),
+                _ => continue,
+            };
+            if !x.is_finite() || !y.is_finite() {
+                continue;
+            }
+            x_range = (x_range.0.min(x), x_range.1.max(x));
+            y_range = (y_range.0.min(y), y_range.1.max(y));
+        }
+        if x_range.0 > x_range.1 {
+            return;
+        }
+        let x_scale = (x_range.1 - x_range.0) / x_factor / (size.width * margin);
+        let y_scale = (y_range.1 - y_range.0) / y_factor / (size.height * margin);
+        // a single point (or a flat line) still needs some finite scale
+        let x_scale = if x_scale > 0.0 {
+            x_scale
+        } else {
+            data.graph_x_scale
+        };
+        let y_scale = if y_scale > 0.0 {
+            y_scale
+        } else {
+            data.graph_y_scale
+        };
+        if !x_scale.is_finite() || !y_scale.is_finite() {
+            return;
+        }
+        data.graph_x_scale = x_scale;
+        data.graph_y_scale = y_scale;
+        self.center = Point::new(
+            size.width / 2.0 - (x_range.0 + x_range.1) / 2.0 / (x_factor * x_scale),
+            size.height / 2.0 + (y_range.0 + y_range.1) / 2.0 / (y_factor * y_scale),
+        );
+    }
+
+    // the axis expressions are compiled again only when they change
+    fn sync_queries(&mut self, data: &AstronomyCanvasData) {
+        self.x_query
+            .set_source(&parse_axis_expression(&data.x_value).0);
+        self.y_query
+            .set_source(&parse_axis_expression(&data.y_value).0);
+    }
+
+    // problems with the axis expressions over the displayed satellites, empty when
+    // every one of them evaluated
+    fn axis_status(&mut self, data: &AstronomyCanvasData) -> String {
+        if !data.graph_view {
+            return String::new();
+        }
+        self.sync_queries(data);
+        let mut queries = vec![("x", &self.x_query)];
+        if !data.histogram_view {
+            queries.push(("y", &self.y_query));
+        }
+        let mut lines = Vec::new();
+        for (axis, query) in queries {
+            for satellite in &self.database.data {
+                query.value(satellite);
+            }
+            let status = query.status(&self.database.data);
+            if !status.is_empty() {
+                lines.push(format!("{}: {}", axis, status));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn ensure_index(&mut self, data: &AstronomyCanvasData) {
+        self.sync_queries(data);
+        let state = IndexState::new(data, self.center, self.size);
+        if self.index_state.as_ref() == Some(&state) {
+            return;
+        }
+        self.index.clear();
+        self.tiles = if self.small_multiples(data) {
+            layout_tiles(self.size, self.database.data.iter())
+        } else {
+            Vec::new()
+        };
+        if data.graph_view {
+            let projection = GraphProjection::new(data, self.center, &self.x_query, &self.y_query);
+            for (position, satellite) in self.database.data.iter().enumerate() {
+                if let Some(point) = projection.project(satellite) {
+                    self.index
+                        .insert(position, Rect::from_center_size(point, (6.0, 6.0)));
+                }
+            }
+        } else {
+            for (position, satellite) in self.database.data.iter().enumerate() {
+                let shape = self.orbit_shape(data, satellite);
+                self.index.insert(position, shape.bounds());
+            }
+        }
+        self.index_state = Some(state);
+    }
+
+    // expects the index to be up to date
+    fn pick(&self, data: &AstronomyCanvasData, point: Point) -> Option<usize> {
+        if self.histogram_view(data) {
+            None
+        } else if data.graph_view {
+            let projection = GraphProjection::new(data, self.center, &self.x_query, &self.y_query);
+            let candidates = self
+                .index
+                .query_point(point, POINT_TOLERANCE)
+                .into_iter()
+                .filter_map(|position| {
+                    let satellite = &self.database.data[position];
+                    let point_position = projection.project(satellite)?;
+                    Some((satellite.id, point_position.distance(point)))
+                });
+            closest(candidates, POINT_TOLERANCE)
+        } else {
+            let candidates = self
+                .index
+                .query_point(point, ORBIT_TOLERANCE)
+                .into_iter()
+                .map(|position| {
+                    let satellite = &self.database.data[position];
+                    let shape = self.orbit_shape(data, satellite);
+                    (satellite.id, shape.distance(point))
+                });
+            closest(candidates, ORBIT_TOLERANCE)
+        }
+    }
+
+    fn histogram_view(&self, data: &AstronomyCanvasData) -> bool {
+        data.graph_view && data.histogram_view
+    }
+
+    // bins the x expression of every displayed satellite
+    fn build_histogram(&self, data: &AstronomyCanvasData) -> Option<Histogram> {
+        let entries = histogram::entries(&self.database.data, &self.x_query, data.x_axis);
+        let width = match eval(&data.bin_width) {
+            Ok(value) => value.as_number().ok(),
+            Err(_) => None,
+        };
+        Histogram::new(&entries, width)
+    }
+
+    // ids in the bar under `point` of the last painted histogram
+    fn histogram_bin(&self, point: Point) -> Option<Vec<usize>> {
+        let (histogram, plot) = self.histogram.as_ref()?;
+        if !plot.contains(point) {
+            return None;
+        }
+        let value = histogram.start
+            + (point.x - plot.x0) / plot.width() * (histogram.end() - histogram.start);
+        let bin = histogram.bin_at(value)?;
+        Some(histogram.bins[bin].iter().map(|(id, _)| *id).collect())
+    }
+
+    fn small_multiples(&self, data: &AstronomyCanvasData) -> bool {
+        !data.graph_view && data.system == SystemView::SmallMultiples
+    }
+
+    // screen origin and km per pixel of the system `satellite` belongs to
+    fn orbit_frame(&self, data: &AstronomyCanvasData, satellite: &Satellite) -> (Point, f64) {
+        if self.small_multiples(data) {
+            if let Some(tile) = self
+                .tiles
+                .iter()
+                .find(|tile| tile.body == satellite.major_body)
+            {
+                return (tile.center, tile.scale);
+            }
+        }
+        (self.center, data.scale * 5000.0)
+    }
+
+    fn orbit_shape(&self, data: &AstronomyCanvasData, satellite: &Satellite) -> OrbitShape {
+        let (origin, scale) = self.orbit_frame(data, satellite);
+        OrbitShape::new(satellite, origin, scale)
+    }
+
+    fn finish_gesture(&mut self, data: &mut AstronomyCanvasData) {
+        if let Some(gesture) = self.gesture.take() {
+            self.ensure_index(data);
+            let hits = self.satellites_in(data, &gesture.polygon(click_size(data)));
+            select(data, &hits, gesture.mode);
+        }
+    }
+
+    fn satellites_in(&self, data: &AstronomyCanvasData, polygon: &[Point]) -> Vec<usize> {
+        let candidates = self.index.query(polygon_bounds(polygon));
+        let satellites = candidates
+            .into_iter()
+            .map(|position| &self.database.data[position]);
+        if data.graph_view {
+            let projection = GraphProjection::new(data, self.center, &self.x_query, &self.y_query);
+            satellites
+                .filter(|satellite| {
+                    projection
+                        .project(satellite)
+                        .map_or(false, |point| contains(polygon, point))
+                })
+                .map(|satellite| satellite.id)
+                .collect()
+        } else {
+            satellites
+                .filter(|satellite| orbit_touches(&self.orbit_shape(data, satellite), polygon))
+                .map(|satellite| satellite.id)
+                .collect()
+        }
+    }
+
+    fn highlight_width(
+        &self,
+        data: &AstronomyCanvasData,
+        satellite: &Satellite,
+        default: f64,
+    ) -> f64 {
+        let selected = self
+            .selected_satellite
+            .map_or(false, |selected| selected.satellite == satellite.id);
+        if selected || self.hovered_satellite == Some(satellite.id) {
+            3.0
+        } else if data
+            .selected_satellites
+            .0
+            .iter()
+            .any(|selected| selected.satellite == satellite.id)
+        {
+            2.0
+        } else {
+            default
+        }
+    }
+
+    fn render_gesture(&self, ctx: &mut PaintCtx, data: &AstronomyCanvasData) {
+        let gesture = match &self.gesture {
+            Some(gesture) => gesture,
+            None => return,
+        };
+        let polygon = gesture.polygon(click_size(data));
+        let mut path = BezPath::new();
+        for (index, point) in polygon.iter().enumerate() {
+            if index == 0 {
+                path.move_to(*point);
+            } else {
+                path.line_to(*point);
+            }
+        }
+        path.close_path();
+        ctx.fill(&path, &Color::AQUA.with_alpha(0.1));
+        ctx.stroke(&path, &Color::AQUA, 1.0);
+    }
+
+    // the satellites of the selected system that pass the filter, with the colours
+    // and the spatial index following them
+    fn refilter(&mut self, data: &AstronomyCanvasData) {
+        if data.match_string.is_empty() {
+            let database: Vec<Satellite> = self
+                .full_database
+                .data
+                .iter()
+                .filter(|satellite| data.system.includes(satellite))
+                .cloned()
+                .collect();
+            self.database = Database { data: database };
+            self.filter_status = String::new();
+        } else {
+            let filter = Query::new(&data.match_string);
+            let candidates: Vec<Satellite> = self
+                .full_database
+                .data
+                .iter()
+                .filter(|satellite| data.system.includes(satellite))
+                .cloned()
+                .collect();
+            let database: Vec<Satellite> = candidates
+                .iter()
+                .filter(|satellite| filter.matches(satellite))
+                .cloned()
+                .collect();
+            // an expression that does not parse is reported apart from one that
+            // simply matches nothing
+            self.filter_status = match filter.error() {
+                Some(_) => filter.status(&candidates),
+                None if database.is_empty() => {
+                    let failures = filter.failure_report(&candidates);
+                    if failures.is_empty() {
+                        "no matches".to_string()
+                    } else {
+                        format!("no matches, {}", failures)
+                    }
+                }
+                None => filter.failure_report(&candidates),
+            };
+            self.database = Database { data: database };
+        }
+        self.index_state = None;
+        self.update_color_mapping(data);
+    }
+
+    fn update_color_mapping(&mut self, data: &AstronomyCanvasData) {
+        self.color_mapping = if data.color_by_expression && !data.color_expression.is_empty() {
+            ColorMapping::by_expression(&data.color_expression, data.colormap, &self.database)
+        } else {
+            ColorMapping::ByMajorBody
+        };
+    }
+
+    fn render_circular_view(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
+        let visible = self.index.query(ctx.size().to_rect());
+        for position in visible {
+            let satellite = &self.database.data[position];
+            let (origin, scale) = self.orbit_frame(data, satellite);
             let a: f64 = satellite.orbital_params.major_semiaxis;
             let e: f64 = satellite.orbital_params.eccentricity;
             let b = (a.powi(2) * (1.0 - e.powi(2))).sqrt();
-            let color = match satellite.major_body {
-                MajorBody::Earth => Color::rgb(0.0, 1.0, 0.4),
-                MajorBody::Mars => Color::rgb(1.0, 0.0, 0.0),
-                MajorBody::Jupiter => Color::rgb(1.0, 0.5, 0.3),
-                MajorBody::Saturn => Color::rgb(1.0, 0.9, 0.3),
-                MajorBody::Uranus => Color::rgb(0.3, 1.0, 0.8),
-                MajorBody::Neptune => Color::rgb(0.1, 0.2, 1.0),
-                MajorBody::Pluto => Color::rgb(0.9, 0.2, 1.0),
-            };
+            let color = self.color_mapping.color(satellite);
 
             let rotation: f64 = satellite.orbital_params.inclination;
             let rotation: f64 = rotation.to_radians();
 
-            let x = self.center.x + ((a * e) * rotation.cos() / scale);
-            let y = self.center.y + ((a * e) * rotation.sin() / scale);
-            let ellipse = Ellipse::new((x, y), (a / scale, b / scale), rotation);
-
-            match data.mouse_point {
-                Some(mouse_point) => {
-                    let x = mouse_point.x;
-                    let y = mouse_point.y;
-                    let rect = Rect::from_center_size((x, y), (40.0, 40.0));
-                    let diagonal = Line::new((rect.x0, rect.y0), (rect.x1, rect.y1));
-                    // let diagonal2 = Line::new((rect.x0, rect.y0), (rect.x1, rect.y1));
-                    ctx.stroke(rect, &Color::AQUA, 1.0);
-                    ctx.stroke(diagonal, &Color::AQUA, 1.0);
-
-                    let ell = ellipse.to_path(0.01);
-                    let ell = ell.segments();
-                    for ell in ell {
-                        if !ell.intersect_line(diagonal).is_empty()
-                            && !self.selected_satellites.contains(satellite)
-                        {
-                            self.selected_satellites.push(satellite.clone());
-                        }
-                    }
-                }
-                None => {
-                    self.selected_satellites.clear();
-                }
-            }
+            let shape = OrbitShape::new(satellite, origin, scale);
+            let (x, y) = (shape.center.x, shape.center.y);
+            let ellipse = shape.to_ellipse();
 
             if data.toggle_major_semiaxes {
                 let x1 = x + (a * rotation.cos() / scale);
@@ -339,23 +887,47 @@ impl AstronomyCanvas {
                 ctx.stroke(minor_semiaxes, &color.clone().with_alpha(0.5), 1.0);
             }
 
-            ctx.stroke(ellipse, &color, 1.0);
+            ctx.stroke(ellipse, &color, self.highlight_width(data, satellite, 1.0));
         }
-        let planet = Ellipse::new(self.center, (5., 5.), 0.0);
-        ctx.fill(planet, &Color::rgb(1.0, 1.0, 0.0));
+        match data.system {
+            SystemView::All => {
+                let planet = Ellipse::new(self.center, (5., 5.), 0.0);
+                ctx.fill(planet, &Color::rgb(1.0, 1.0, 0.0));
+            }
+            SystemView::Single(body) => {
+                render_parent(ctx, body, self.center, data.scale * 5000.0);
+            }
+            SystemView::SmallMultiples => {
+                for tile in &self.tiles {
+                    ctx.stroke(tile.rect, &Color::grey(0.3), 1.0);
+                    render_parent(ctx, tile.body, tile.center, tile.scale);
+                    let title = ctx
+                        .text()
+                        .new_text_layout(tile.body.to_string())
+                        .text_color(major_body_color(tile.body))
+                        .build()
+                        .unwrap();
+                    ctx.draw_text(&title, (tile.rect.x0 + 5.0, tile.rect.y0 + 3.0));
+                }
+                return;
+            }
+        }
+        let scale = data.scale * 5000.0;
 
         if data.toggle_distance {
             let line = Line::new(self.center, (ctx.size().width, self.center.y));
             ctx.stroke(line, &Color::WHITE, 2.0);
-            for i in 0..50 {
-                let distance = i as f64 * 80.0;
+            let width = ctx.size().width - self.center.x;
+            let ticks = linear_ticks(0.0, width * scale, (width / 80.0) as usize);
+            for tick in ticks {
+                let distance = tick.value / scale;
                 let line = Line::new(
                     (self.center.x + distance, self.center.y - 15.0),
                     (self.center.x + distance, self.center.y + 15.0),
                 );
                 let text = ctx.text();
                 let text = text
-                    .new_text_layout((distance * scale).to_string())
+                    .new_text_layout(format!("{} km", tick.label))
                     .text_color(Color::WHITE);
                 let text = text.build().unwrap();
                 ctx.draw_text(&text, (self.center.x + distance, self.center.y + 20.0));
@@ -391,90 +963,111 @@ impl AstronomyCanvas {
             }
         }
     }
-    fn render_graph_view(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
-        let x_values: Vec<&str> = data
-            .x_value
-            .split('|')
-            .filter(|&value| !value.is_empty() && value != " ")
-            .collect();
-
-        let mut x_scale = 1.0;
-        let mut x_value = String::new();
-        if !x_values.is_empty() {
-            x_value = x_values[0].to_string();
-        }
-        if x_values.len() > 1 {
-            let value = x_values[1];
-            let value = eval(value);
-            let value: f64 = match value {
-                Ok(value) => value.as_number().unwrap_or(1.0),
-                Err(_) => 1.0,
-            };
-            x_scale = value;
+    // lines between the satellites of each pair found by the pair query, drawn under
+    // the points; pairs with a satellite filtered out are left out
+    fn render_pairs(&self, ctx: &mut PaintCtx, data: &AstronomyCanvasData) {
+        if data.pairs.is_empty() {
+            return;
         }
-        x_scale *= data.scale;
-
-        let y_values: Vec<&str> = data
-            .y_value
-            .split('|')
-            .filter(|&value| !value.is_empty() && value != " ")
+        let projection = GraphProjection::new(data, self.center, &self.x_query, &self.y_query);
+        let satellites: HashMap<usize, &Satellite> = self
+            .database
+            .data
+            .iter()
+            .map(|satellite| (satellite.id, satellite))
             .collect();
-        let mut y_value = String::new();
-
-        let mut y_scale = 1.0;
-        if !y_values.is_empty() {
-            y_value = y_values[0].to_string();
-        }
-        if y_values.len() > 1 {
-            let value = y_values[1];
-            let value = eval(value);
-            let value: f64 = match value {
-                Ok(value) => value.as_number().unwrap_or(1.0),
-                Err(_) => 1.0,
-            };
-            y_scale = value;
+        let color = Color::rgb8(0xff, 0xa5, 0x00).with_alpha(0.6);
+        for (first, second) in data.pairs.iter() {
+            let points = satellites
+                .get(first)
+                .and_then(|satellite| projection.project(satellite))
+                .zip(
+                    satellites
+                        .get(second)
+                        .and_then(|satellite| projection.project(satellite)),
+                );
+            if let Some((from, to)) = points {
+                ctx.stroke(Line::new(from, to), &color, 1.0);
+            }
         }
-        y_scale *= data.scale;
+    }
 
-        let x_line = Line::new(
-            (self.center.x - 1000.0, self.center.y),
-            (self.center.x + 1000.0, self.center.y),
-        );
-        let y_line = Line::new(
-            (self.center.x, self.center.y - 1000.0),
-            (self.center.x, self.center.y + 1000.0),
-        );
+    fn render_graph_view(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
+        let GraphProjection {
+            x_query,
+            y_query,
+            x_scale,
+            y_scale,
+            ..
+        } = GraphProjection::new(data, self.center, &self.x_query, &self.y_query);
+
+        let size = ctx.size();
+        let x_line = Line::new((0.0, self.center.y), (size.width, self.center.y));
+        let y_line = Line::new((self.center.x, 0.0), (self.center.x, size.height));
         ctx.stroke(x_line, &Color::WHITE, 2.0);
         ctx.stroke(y_line, &Color::WHITE, 2.0);
-        for i in -50..50 {
-            let distance = i as f64 * 80.0;
-            let x_line = Line::new(
-                (self.center.x + distance, self.center.y + 10.0),
-                (self.center.x + distance, self.center.y - 10.0),
-            );
-            let y_line = Line::new(
-                (self.center.x + 10.0, self.center.y + distance),
-                (self.center.x - 10.0, self.center.y + distance),
-            );
-            ctx.stroke(x_line, &Color::WHITE, 1.0);
-            ctx.stroke(y_line, &Color::WHITE, 1.0);
-            let text = ctx.text();
-            let text_x = text
-                .new_text_layout((distance * x_scale).to_string())
-                .text_color(Color::WHITE);
-            let text_x = text_x.build().unwrap();
-            let text_y = text
-                .new_text_layout((distance * y_scale).to_string())
-                .text_color(Color::WHITE);
-            let text_y = text_y.build().unwrap();
-
-            ctx.draw_text(&text_y, (self.center.x - 20.0, self.center.y + distance));
-            ctx.draw_text(&text_x, (self.center.x + distance, self.center.y + 20.0));
+
+        let x_ticks = data.x_axis.ticks(
+            -self.center.x * x_scale,
+            (size.width - self.center.x) * x_scale,
+            (size.width / 80.0) as usize,
+        );
+        for tick in x_ticks {
+            let x = self.center.x + data.x_axis.forward(tick.value) / x_scale;
+            let line = Line::new((x, self.center.y + 10.0), (x, self.center.y - 10.0));
+            ctx.stroke(line, &Color::WHITE, 1.0);
+            let text = ctx
+                .text()
+                .new_text_layout(tick.label)
+                .text_color(Color::WHITE)
+                .build()
+                .unwrap();
+            ctx.draw_text(&text, (x, self.center.y + 20.0));
+        }
+        let y_ticks = data.y_axis.ticks(
+            (self.center.y - size.height) * y_scale,
+            self.center.y * y_scale,
+            (size.height / 80.0) as usize,
+        );
+        for tick in y_ticks {
+            let y = self.center.y - data.y_axis.forward(tick.value) / y_scale;
+            let line = Line::new((self.center.x + 10.0, y), (self.center.x - 10.0, y));
+            ctx.stroke(line, &Color::WHITE, 1.0);
+            let text = ctx
+                .text()
+                .new_text_layout(tick.label)
+                .text_color(Color::WHITE)
+                .build()
+                .unwrap();
+            let width = text.size().width;
+            ctx.draw_text(&text, (self.center.x - 15.0 - width, y));
         }
 
-        for satellite in &self.database.data {
-            let x = parse_math_expression(satellite, &x_value);
-            let y = parse_math_expression(satellite, &y_value);
+        self.render_pairs(ctx, data);
+
+        let visible = self.index.query(size.to_rect());
+        for position in visible {
+            let satellite = &self.database.data[position];
+            // satellites whose position uses an unknown value are not plotted
+            if x_query.known_value(satellite).is_none() || y_query.known_value(satellite).is_none()
+            {
+                continue;
+            }
+            let (x_raw, x_error) = if data.toggle_error_bars {
+                x_query.value_with_error(satellite)
+            } else {
+                (x_query.value(satellite), Some(0.0))
+            };
+            let (y_raw, y_error) = if data.toggle_error_bars {
+                y_query.value_with_error(satellite)
+            } else {
+                (y_query.value(satellite), Some(0.0))
+            };
+            let x = data.x_axis.forward(x_raw);
+            let y = data.y_axis.forward(y_raw);
+            if !x.is_finite() || !y.is_finite() {
+                continue;
+            }
 
             let x = x / x_scale;
             let y = y / y_scale;
@@ -484,88 +1077,345 @@ impl AstronomyCanvas {
 
             let ellipse = Ellipse::new((x, y), (3.0, 3.0), 0.0);
 
-            match data.mouse_point {
-                Some(mouse_point) => {
-                    let rect = Rect::from_center_size((mouse_point.x, mouse_point.y), (20.0, 20.0));
-                    if x < rect.x1
-                        && x > rect.x0
-                        && y < rect.y1
-                        && y > rect.y0
-                        && !self.selected_satellites.contains(satellite)
-                    {
-                        self.selected_satellites.push(satellite.clone());
-                    }
-                    ctx.stroke(rect, &Color::AQUA, 1.0);
+            let color = self.color_mapping.color(satellite);
+            if data.toggle_error_bars {
+                if let Some(x_error) = x_error.filter(|&error| error > 0.0) {
+                    let to_screen = |value: f64| {
+                        let value = data.x_axis.forward(value);
+                        if value.is_finite() {
+                            self.center.x + value / x_scale
+                        } else {
+                            x
+                        }
+                    };
+                    let left = to_screen(x_raw - x_error);
+                    let right = to_screen(x_raw + x_error);
+                    ctx.stroke(Line::new((left, y), (right, y)), &color, 1.0);
+                    ctx.stroke(Line::new((left, y - 3.0), (left, y + 3.0)), &color, 1.0);
+                    ctx.stroke(Line::new((right, y - 3.0), (right, y + 3.0)), &color, 1.0);
                 }
-                None => {
-                    self.selected_satellites.clear();
+                if let Some(y_error) = y_error.filter(|&error| error > 0.0) {
+                    let to_screen = |value: f64| {
+                        let value = data.y_axis.forward(value);
+                        if value.is_finite() {
+                            self.center.y - value / y_scale
+                        } else {
+                            y
+                        }
+                    };
+                    let bottom = to_screen(y_raw - y_error);
+                    let top = to_screen(y_raw + y_error);
+                    ctx.stroke(Line::new((x, bottom), (x, top)), &color, 1.0);
+                    ctx.stroke(Line::new((x - 3.0, bottom), (x + 3.0, bottom)), &color, 1.0);
+                    ctx.stroke(Line::new((x - 3.0, top), (x + 3.0, top)), &color, 1.0);
                 }
             }
+            let width = self.highlight_width(data, satellite, 0.0);
+            if width > 0.0 {
+                ctx.stroke(ellipse, &Color::WHITE, width);
+            }
+            ctx.fill(ellipse, &color);
+        }
+    }
 
-            let color = match satellite.major_body {
-                MajorBody::Earth => Color::rgb(0.0, 1.0, 0.4),
-                MajorBody::Mars => Color::rgb(1.0, 0.0, 0.0),
-                MajorBody::Jupiter => Color::rgb(1.0, 0.5, 0.3),
-                MajorBody::Saturn => Color::rgb(1.0, 0.9, 0.3),
-                MajorBody::Uranus => Color::rgb(0.3, 1.0, 0.8),
-                MajorBody::Neptune => Color::rgb(0.1, 0.2, 1.0),
-                MajorBody::Pluto => Color::rgb(0.9, 0.2, 1.0),
+    fn render_histogram(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData) {
+        self.histogram = None;
+        let histogram = match self.build_histogram(data) {
+            Some(histogram) => histogram,
+            None => return,
+        };
+        let size = ctx.size();
+        let plot = Rect::new(60.0, 30.0, size.width - 20.0, size.height - 40.0);
+        let range = histogram.end() - histogram.start;
+        let to_value = |x: f64| histogram.start + (x - plot.x0) / plot.width() * range;
+        let x_to_screen = |value: f64| plot.x0 + (value - histogram.start) / range * plot.width();
+
+        let curves: Vec<(Color, Vec<(f64, f64)>)> = if data.toggle_kde {
+            let bodies: Vec<Option<MajorBody>> = match data.histogram_style {
+                HistogramStyle::Overlaid => MajorBody::ALL
+                    .iter()
+                    .copied()
+                    .filter(|body| histogram.values.iter().any(|value| value.0 == *body))
+                    .map(Some)
+                    .collect(),
+                _ => vec![None],
             };
-            ctx.fill(ellipse, &color);
+            bodies
+                .into_iter()
+                .map(|body| {
+                    let color = body.map_or(Color::WHITE, major_body_color);
+                    let points: Vec<(f64, f64)> = (0..=(plot.width().max(0.0) / 2.0) as usize)
+                        .map(|step| {
+                            let x = plot.x0 + step as f64 * 2.0;
+                            (x, histogram.density(to_value(x), body))
+                        })
+                        .collect();
+                    (color, points)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let highest = curves
+            .iter()
+            .flat_map(|(_, points)| points.iter().map(|point| point.1))
+            .fold(histogram.max_count() as f64, f64::max);
+        let top = highest.max(1.0) * 1.1;
+        let y_to_screen = |count: f64| plot.y1 - count / top * plot.height();
+
+        for (bin, satellites) in histogram.bins.iter().enumerate() {
+            let left = x_to_screen(histogram.start + bin as f64 * histogram.width);
+            let right = x_to_screen(histogram.start + (bin + 1) as f64 * histogram.width);
+            let bar = |bottom: usize, count: usize| {
+                Rect::new(
+                    left,
+                    y_to_screen((bottom + count) as f64),
+                    right,
+                    y_to_screen(bottom as f64),
+                )
+            };
+            match data.histogram_style {
+                HistogramStyle::Plain => {
+                    let rect = bar(0, satellites.len());
+                    ctx.fill(rect, &Color::grey(0.6));
+                    ctx.stroke(rect, &Color::grey(0.2), 1.0);
+                }
+                HistogramStyle::Stacked => {
+                    let mut bottom = 0;
+                    for body in MajorBody::ALL {
+                        let count = histogram.count(bin, Some(body));
+                        if count > 0 {
+                            ctx.fill(bar(bottom, count), &major_body_color(body));
+                            bottom += count;
+                        }
+                    }
+                }
+                HistogramStyle::Overlaid => {
+                    for body in MajorBody::ALL {
+                        let count = histogram.count(bin, Some(body));
+                        if count > 0 {
+                            let rect = bar(0, count);
+                            ctx.fill(rect, &major_body_color(body).with_alpha(0.25));
+                            ctx.stroke(rect, &major_body_color(body), 1.0);
+                        }
+                    }
+                }
+            }
+            // the selected part of each bar, so brushing in other views shows up here
+            let selected = satellites
+                .iter()
+                .filter(|(id, _)| {
+                    data.selected_satellites
+                        .0
+                        .iter()
+                        .any(|wrapper| wrapper.satellite == *id)
+                })
+                .count();
+            if selected > 0 {
+                let rect = bar(0, selected);
+                ctx.fill(rect, &Color::AQUA.with_alpha(0.3));
+                ctx.stroke(rect, &Color::AQUA, 2.0);
+            }
         }
+
+        for (color, points) in &curves {
+            let mut path = BezPath::new();
+            for (index, (x, density)) in points.iter().enumerate() {
+                let point = Point::new(*x, y_to_screen(*density));
+                if index == 0 {
+                    path.move_to(point);
+                } else {
+                    path.line_to(point);
+                }
+            }
+            ctx.stroke(&path, color, 2.0);
+        }
+
+        let x_line = Line::new((plot.x0, plot.y1), (plot.x1, plot.y1));
+        let y_line = Line::new((plot.x0, plot.y0), (plot.x0, plot.y1));
+        ctx.stroke(x_line, &Color::WHITE, 2.0);
+        ctx.stroke(y_line, &Color::WHITE, 2.0);
+        let x_ticks = data.x_axis.ticks(
+            histogram.start,
+            histogram.end(),
+            (plot.width() / 80.0) as usize,
+        );
+        for tick in x_ticks {
+            let x = x_to_screen(data.x_axis.forward(tick.value));
+            ctx.stroke(
+                Line::new((x, plot.y1), (x, plot.y1 + 6.0)),
+                &Color::WHITE,
+                1.0,
+            );
+            let text = ctx
+                .text()
+                .new_text_layout(tick.label)
+                .text_color(Color::WHITE)
+                .build()
+                .unwrap();
+            ctx.draw_text(&text, (x - text.size().width / 2.0, plot.y1 + 10.0));
+        }
+        for tick in linear_ticks(0.0, top, (plot.height() / 60.0) as usize) {
+            let y = y_to_screen(tick.value);
+            ctx.stroke(
+                Line::new((plot.x0 - 6.0, y), (plot.x0, y)),
+                &Color::WHITE,
+                1.0,
+            );
+            let text = ctx
+                .text()
+                .new_text_layout(tick.label)
+                .text_color(Color::WHITE)
+                .build()
+                .unwrap();
+            let text_size = text.size();
+            ctx.draw_text(
+                &text,
+                (plot.x0 - 10.0 - text_size.width, y - text_size.height / 2.0),
+            );
+        }
+        let title = ctx
+            .text()
+            .new_text_layout(format!(
+                "{} satellites, bin width {:.4}",
+                histogram.values.len(),
+                histogram.width
+            ))
+            .text_color(Color::WHITE)
+            .build()
+            .unwrap();
+        ctx.draw_text(&title, (plot.x0 + 10.0, 5.0));
+        self.histogram = Some((histogram, plot));
+    }
+
+    fn render_tooltip(&self, ctx: &mut PaintCtx) {
+        let (id, point) = match (self.hovered_satellite, self.hover_point) {
+            (Some(id), Some(point)) => (id, point),
+            _ => return,
+        };
+        let satellite = match self
+            .database
+            .data
+            .iter()
+            .find(|satellite| satellite.id == id)
+        {
+            Some(satellite) => satellite,
+            None => return,
+        };
+        let text = ctx
+            .text()
+            .new_text_layout(format!(
+                "{} ({})",
+                satellite.name,
+                satellite.major_body.to_string()
+            ))
+            .text_color(Color::WHITE)
+            .build()
+            .unwrap();
+        let origin = Point::new(point.x + 12.0, point.y + 12.0);
+        let background = Rect::from_origin_size(origin, text.size()).inflate(4.0, 2.0);
+        ctx.fill(background, &Color::BLACK.with_alpha(0.8));
+        ctx.stroke(background, &Color::grey(0.6), 1.0);
+        ctx.draw_text(&text, origin);
     }
 }
 
-fn parse_logicall_expression(satellite: &Satellite, logical_expression: &str) -> bool {
-    let precompiled = build_operator_tree(logical_expression);
-    let precompiled = match precompiled {
-        Ok(precompiled) => precompiled,
-        Err(_e) => build_operator_tree("false").unwrap(),
-    };
-    let context = context_map! {
-
-        "a" => satellite.orbital_params.major_semiaxis,
-        "i" => satellite.orbital_params.inclination,
-        "e" => satellite.orbital_params.eccentricity,
-        "node" => satellite.orbital_params.ascending_node,
-        "gm" => satellite.physical_params.gm.to_value(),
-        "radius" => satellite.physical_params.radius.to_value(),
-        "density" => satellite.physical_params.density.to_value(),
-        "magnitude" => satellite.physical_params.magnitude.to_value(),
-        "albedo" => satellite.physical_params.albedo.to_value(),
-        "mb" => satellite.major_body.to_string(),
-        "name" => satellite.name.clone()
-    }
-    .unwrap();
-    let result = precompiled.eval_boolean_with_context(&context);
-    match result {
-        Ok(result) => result,
-        _ => false,
+struct GraphProjection<'a> {
+    x_query: &'a Query,
+    y_query: &'a Query,
+    x_axis: AxisScale,
+    y_axis: AxisScale,
+    x_scale: f64,
+    y_scale: f64,
+    center: Point,
+}
+
+impl<'a> GraphProjection<'a> {
+    fn new(
+        data: &AstronomyCanvasData,
+        center: Point,
+        x_query: &'a Query,
+        y_query: &'a Query,
+    ) -> GraphProjection<'a> {
+        let (_, x_scale) = parse_axis_expression(&data.x_value);
+        let (_, y_scale) = parse_axis_expression(&data.y_value);
+        GraphProjection {
+            x_query,
+            y_query,
+            x_axis: data.x_axis,
+            y_axis: data.y_axis,
+            x_scale: x_scale * data.x_axis.units_per_pixel() * data.graph_x_scale,
+            y_scale: y_scale * data.y_axis.units_per_pixel() * data.graph_y_scale,
+            center,
+        }
+    }
+
+    // None when a value is unknown or the point is off the axis scale
+    fn project(&self, satellite: &Satellite) -> Option<Point> {
+        let x = self.x_axis.forward(self.x_query.known_value(satellite)?);
+        let y = self.y_axis.forward(self.y_query.known_value(satellite)?);
+        if !x.is_finite() || !y.is_finite() {
+            return None;
+        }
+        Some(Point::new(
+            self.center.x + x / self.x_scale,
+            self.center.y - y / self.y_scale,
+        ))
     }
 }
 
-fn parse_math_expression(satellite: &Satellite, math_expression: &str) -> f64 {
-    let precompiled = build_operator_tree(math_expression);
-    let precompiled = match precompiled {
-        Ok(precompiled) => precompiled,
-        Err(_e) => build_operator_tree("false").unwrap(),
-    };
-    let context = context_map! {
-        "a" => satellite.orbital_params.major_semiaxis,
-        "i" => satellite.orbital_params.inclination,
-        "e" => satellite.orbital_params.eccentricity,
-        "node" => satellite.orbital_params.ascending_node,
-        "gm" => satellite.physical_params.gm.to_value(),
-        "radius" => satellite.physical_params.radius.to_value(),
-        "density" => satellite.physical_params.density.to_value(),
-        "magnitude" => satellite.physical_params.magnitude.to_value(),
-        "albedo" => satellite.physical_params.albedo.to_value(),
-    }
-    .unwrap();
-
-    let result = precompiled.eval_float_with_context(&context);
-    match result {
-        Ok(result) => result,
-        _ => 0.0,
+// the parent body drawn to scale, but never smaller than a few pixels
+fn render_parent(ctx: &mut PaintCtx, body: MajorBody, center: Point, scale: f64) {
+    let radius = (body.radius() / scale).max(3.0);
+    let planet = Ellipse::new(center, (radius, radius), 0.0);
+    ctx.fill(planet, &major_body_color(body));
+}
+
+// side of the square selected by a right click without dragging
+fn click_size(data: &AstronomyCanvasData) -> f64 {
+    if data.graph_view {
+        20.0
+    } else {
+        40.0
     }
 }
+
+pub fn select(data: &mut AstronomyCanvasData, hits: &[usize], mode: SelectionMode) {
+    let current: Vec<usize> = data
+        .selected_satellites
+        .0
+        .iter()
+        .map(|wrapper| wrapper.satellite)
+        .collect();
+    let selection = apply(&current, hits, mode)
+        .into_iter()
+        .map(|satellite| {
+            data.selected_satellites
+                .0
+                .iter()
+                .find(|wrapper| wrapper.satellite == satellite)
+                .copied()
+                .unwrap_or(SatelliteWrapper {
+                    satellite,
+                    selected: false,
+                })
+        })
+        .collect();
+    data.selected_satellites = SatelliteteVec(selection);
+}
+
+fn parse_axis_expression(value: &str) -> (String, f64) {
+    let values: Vec<&str> = value
+        .split('|')
+        .filter(|&value| !value.is_empty() && value != " ")
+        .collect();
+    let expression = values
+        .first()
+        .map(|value| value.to_string())
+        .unwrap_or_default();
+    let scale = match values.get(1).map(|value| eval(value)) {
+        Some(Ok(value)) => value.as_number().unwrap_or(1.0),
+        _ => 1.0,
+    };
+    (expression, scale)
+}
@@ -0,0 +1,263 @@
+use druid::Data;
+
+// linear threshold of the symlog transform: values in (-1, 1) stay roughly linear
+const SYMLOG_THRESHOLD: f64 = 1.0;
+
+#[derive(Clone, Copy, Data, PartialEq, Debug)]
+pub enum AxisScale {
+    Linear,
+    Log,
+    Symlog,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tick {
+    pub value: f64,
+    pub label: String,
+}
+
+impl AxisScale {
+    pub fn forward(&self, value: f64) -> f64 {
+        match self {
+            AxisScale::Linear => value,
+            AxisScale::Log => {
+                if value > 0.0 {
+                    value.log10()
+                } else {
+                    f64::NAN
+                }
+            }
+            AxisScale::Symlog => value.signum() * (1.0 + value.abs() / SYMLOG_THRESHOLD).log10(),
+        }
+    }
+
+    pub fn inverse(&self, transformed: f64) -> f64 {
+        match self {
+            AxisScale::Linear => transformed,
+            AxisScale::Log => 10f64.powf(transformed),
+            AxisScale::Symlog => {
+                transformed.signum() * SYMLOG_THRESHOLD * (10f64.powf(transformed.abs()) - 1.0)
+            }
+        }
+    }
+
+    // amount of transformed units covered by one pixel at zoom 1
+    pub fn units_per_pixel(&self) -> f64 {
+        match self {
+            AxisScale::Linear => 1.0,
+            AxisScale::Log | AxisScale::Symlog => 0.01,
+        }
+    }
+
+    // ticks for the visible range given in transformed units
+    pub fn ticks(&self, min: f64, max: f64, max_ticks: usize) -> Vec<Tick> {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        let max_ticks = max_ticks.max(2);
+        if !min.is_finite() || !max.is_finite() || min == max {
+            return Vec::new();
+        }
+        match self {
+            AxisScale::Linear => linear_ticks(min, max, max_ticks),
+            AxisScale::Log => {
+                if max - min < 1.0 {
+                    return linear_ticks(self.inverse(min), self.inverse(max), max_ticks);
+                }
+                let stride = ((max - min) / max_ticks as f64).ceil().max(1.0) as i64;
+                let first = (min.ceil() as i64).div_euclid(stride) * stride;
+                (0..)
+                    .map(|i| first + i * stride)
+                    .take_while(|&exponent| exponent as f64 <= max)
+                    .filter(|&exponent| exponent as f64 >= min)
+                    .map(|exponent| Tick {
+                        value: 10f64.powi(exponent as i32),
+                        label: power_of_ten_label(exponent),
+                    })
+                    .collect()
+            }
+            AxisScale::Symlog => {
+                let largest = self.inverse(min.abs().max(max.abs()));
+                let max_exponent = largest.log10().ceil().max(0.0) as i64;
+                let stride = ((2 * max_exponent + 1) as f64 / max_ticks as f64)
+                    .ceil()
+                    .max(1.0) as i64;
+                let mut ticks = vec![Tick {
+                    value: 0.0,
+                    label: "0".to_string(),
+                }];
+                for exponent in (0..=max_exponent).filter(|exponent| exponent % stride == 0) {
+                    let value = 10f64.powi(exponent as i32);
+                    ticks.push(Tick {
+                        value,
+                        label: power_of_ten_label(exponent),
+                    });
+                    ticks.push(Tick {
+                        value: -value,
+                        label: format!("-{}", power_of_ten_label(exponent)),
+                    });
+                }
+                ticks
+                    .into_iter()
+                    .filter(|tick| {
+                        let transformed = self.forward(tick.value);
+                        transformed >= min && transformed <= max
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+pub fn nice_step(range: f64, max_ticks: usize) -> f64 {
+    let raw_step = range / max_ticks as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let normalized = raw_step / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+pub fn linear_ticks(min: f64, max: f64, max_ticks: usize) -> Vec<Tick> {
+    let step = nice_step(max - min, max_ticks);
+    if !step.is_finite() || step <= 0.0 {
+        return Vec::new();
+    }
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last)
+        .map(|i| {
+            let value = i as f64 * step;
+            Tick {
+                value,
+                label: format_tick(value, step),
+            }
+        })
+        .collect()
+}
+
+fn power_of_ten_label(exponent: i64) -> String {
+    let value = 10f64.powi(exponent as i32);
+    format_tick(value, value)
+}
+
+const SI_PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+
+// formats a tick value with just enough digits to tell it apart from its neighbours
+// that are `step` away, using SI prefixes for large values and scientific notation
+// for tiny ones
+pub fn format_tick(value: f64, step: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return "0".to_string();
+    }
+    let step = step.abs();
+    let magnitude = value.abs().log10().floor();
+    if magnitude >= 3.0 && magnitude < 3.0 * SI_PREFIXES.len() as f64 {
+        let prefix = (magnitude / 3.0).floor() as usize;
+        let divisor = 10f64.powi(3 * prefix as i32);
+        let decimals = decimals_for_step(step / divisor);
+        return format!("{:.*}{}", decimals, value / divisor, SI_PREFIXES[prefix]);
+    }
+    if !(-3.0..3.0).contains(&magnitude) {
+        let step_magnitude = step.log10().floor();
+        let digits = if step_magnitude.is_finite() {
+            (magnitude - step_magnitude).max(0.0) as usize
+        } else {
+            0
+        };
+        let mantissa = value / 10f64.powf(magnitude);
+        return format!("{:.*}e{}", digits, mantissa, magnitude as i64);
+    }
+    format!("{:.*}", decimals_for_step(step), value)
+}
+
+fn decimals_for_step(step: f64) -> usize {
+    if step > 0.0 && step.is_finite() {
+        (-step.log10().floor()).max(0.0) as usize
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    fn values(ticks: &[Tick]) -> Vec<f64> {
+        ticks.iter().map(|tick| tick.value).collect()
+    }
+
+    fn labels(ticks: &[Tick]) -> Vec<&str> {
+        ticks.iter().map(|tick| tick.label.as_str()).collect()
+    }
+
+    #[test]
+    fn transforms_round_trip() {
+        for value in [1e-3, 0.5, 1.0, 42.0, 1e6] {
+            assert!(close(
+                AxisScale::Log.inverse(AxisScale::Log.forward(value)),
+                value
+            ));
+        }
+        assert!(AxisScale::Log.forward(0.0).is_nan());
+        assert!(AxisScale::Log.forward(-1.0).is_nan());
+        for value in [-1e6, -3.5, -0.2, 0.0, 0.2, 3.5, 1e6] {
+            let transformed = AxisScale::Symlog.forward(value);
+            assert!(close(AxisScale::Symlog.inverse(transformed), value));
+        }
+        assert_eq!(AxisScale::Symlog.forward(-9.0), -1.0);
+    }
+
+    #[test]
+    fn ticks_follow_the_scale() {
+        let decades = AxisScale::Log.ticks(0.0, 3.0, 10);
+        assert_eq!(values(&decades), vec![1.0, 10.0, 100.0, 1000.0]);
+        assert_eq!(labels(&decades), vec!["1", "10", "100", "1k"]);
+        // too many decades for the ticks skip some
+        let sparse = AxisScale::Log.ticks(-0.5, 12.0, 4);
+        assert_eq!(values(&sparse), vec![1.0, 1e4, 1e8, 1e12]);
+
+        let symlog = AxisScale::Symlog;
+        let mut across = values(&symlog.ticks(symlog.forward(-100.0), symlog.forward(50.0), 10));
+        across.sort_by(f64::total_cmp);
+        assert_eq!(across, vec![-100.0, -10.0, -1.0, 0.0, 1.0, 10.0]);
+
+        let linear = AxisScale::Linear.ticks(0.0, 1.0, 10);
+        assert_eq!(linear.len(), 11);
+        assert_eq!(linear[3].label, "0.3");
+        assert!(AxisScale::Linear.ticks(2.0, 2.0, 10).is_empty());
+    }
+
+    #[test]
+    fn steps_are_nice_numbers() {
+        assert!(close(nice_step(7.3, 5), 2.0));
+        assert!(close(nice_step(0.0031, 10), 5e-4));
+        assert!(close(nice_step(95.0, 10), 10.0));
+        assert!(close(nice_step(1.0, 10), 0.1));
+        assert!(close(nice_step(3e9, 7), 5e8));
+    }
+
+    #[test]
+    fn labels_drop_rounding_noise() {
+        assert_eq!(format_tick(0.1 + 0.2, 0.1), "0.3");
+        assert_eq!(format_tick(0.0, 0.1), "0");
+        assert_eq!(format_tick(999.0, 1.0), "999");
+        assert_eq!(format_tick(1000.0, 1000.0), "1k");
+        assert_eq!(format_tick(1500.0, 500.0), "1.5k");
+        assert_eq!(format_tick(-2000.0, 1000.0), "-2k");
+        assert_eq!(format_tick(2.5e6, 5e5), "2.5M");
+        assert_eq!(format_tick(1e12, 1e12), "1T");
+        assert_eq!(format_tick(1e15, 1e15), "1e15");
+        assert_eq!(format_tick(0.001, 0.001), "0.001");
+        assert_eq!(format_tick(1e-4, 1e-4), "1e-4");
+    }
+}
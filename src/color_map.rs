@@ -0,0 +1,257 @@
+use druid::kurbo::Rect;
+use druid::piet::{Color, Text, TextLayoutBuilder};
+use druid::{Data, PaintCtx, RenderContext};
+use satellite_data::{
+    database::Database,
+    satellites::{MajorBody, Satellite},
+};
+
+use crate::query::Query;
+
+#[derive(Clone, Copy, Data, PartialEq, Debug)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Inferno,
+    Cividis,
+}
+
+impl Colormap {
+    fn anchors(&self) -> [(u8, u8, u8); 9] {
+        match self {
+            Colormap::Viridis => [
+                (68, 1, 84),
+                (71, 44, 122),
+                (59, 81, 139),
+                (44, 113, 142),
+                (33, 144, 141),
+                (39, 173, 129),
+                (92, 200, 99),
+                (170, 220, 50),
+                (253, 231, 37),
+            ],
+            Colormap::Magma => [
+                (0, 0, 4),
+                (28, 16, 68),
+                (79, 18, 123),
+                (129, 37, 129),
+                (181, 54, 122),
+                (229, 80, 100),
+                (251, 135, 97),
+                (254, 194, 135),
+                (252, 253, 191),
+            ],
+            Colormap::Inferno => [
+                (0, 0, 4),
+                (31, 12, 72),
+                (85, 15, 109),
+                (136, 34, 106),
+                (186, 54, 85),
+                (227, 89, 51),
+                (249, 140, 10),
+                (249, 201, 50),
+                (252, 255, 164),
+            ],
+            Colormap::Cividis => [
+                (0, 32, 77),
+                (0, 51, 111),
+                (57, 72, 107),
+                (87, 93, 109),
+                (112, 113, 115),
+                (138, 135, 121),
+                (166, 157, 117),
+                (196, 181, 108),
+                (254, 232, 56),
+            ],
+        }
+    }
+
+    pub fn color(&self, t: f64) -> Color {
+        let anchors = self.anchors();
+        let t = if t.is_finite() {
+            t.max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+        let position = t * (anchors.len() - 1) as f64;
+        let index = (position.floor() as usize).min(anchors.len() - 2);
+        let fraction = position - index as f64;
+        let (r0, g0, b0) = anchors[index];
+        let (r1, g1, b1) = anchors[index + 1];
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * fraction) / 255.0;
+        Color::rgb(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+    }
+}
+
+pub fn major_body_color(major_body: MajorBody) -> Color {
+    match major_body {
+        MajorBody::Earth => Color::rgb(0.0, 1.0, 0.4),
+        MajorBody::Mars => Color::rgb(1.0, 0.0, 0.0),
+        MajorBody::Jupiter => Color::rgb(1.0, 0.5, 0.3),
+        MajorBody::Saturn => Color::rgb(1.0, 0.9, 0.3),
+        MajorBody::Uranus => Color::rgb(0.3, 1.0, 0.8),
+        MajorBody::Neptune => Color::rgb(0.1, 0.2, 1.0),
+        MajorBody::Pluto => Color::rgb(0.9, 0.2, 1.0),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ColorMapping {
+    ByMajorBody,
+    ByExpression {
+        query: Query,
+        colormap: Colormap,
+        min: f64,
+        max: f64,
+    },
+}
+
+impl ColorMapping {
+    pub fn by_expression(expression: &str, colormap: Colormap, database: &Database) -> Self {
+        let query = Query::new(expression);
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        // unknown values stay grey and out of the range
+        for satellite in &database.data {
+            if let Some(value) = query.known_value(satellite) {
+                min = min.min(value);
+                max = max.max(value);
+            }
+        }
+        if min > max {
+            min = 0.0;
+            max = 1.0;
+        }
+        ColorMapping::ByExpression {
+            query,
+            colormap,
+            min,
+            max,
+        }
+    }
+
+    pub fn color(&self, satellite: &Satellite) -> Color {
+        match self {
+            ColorMapping::ByMajorBody => major_body_color(satellite.major_body),
+            ColorMapping::ByExpression {
+                query,
+                colormap,
+                min,
+                max,
+            } => {
+                let value = match query.known_value(satellite) {
+                    Some(value) => value,
+                    None => return Color::grey(0.5),
+                };
+                let t = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.5
+                };
+                colormap.color(t)
+            }
+        }
+    }
+
+    pub fn draw_legend(&self, ctx: &mut PaintCtx, database: &Database) {
+        let right = ctx.size().width - 10.0;
+        match self {
+            ColorMapping::ByMajorBody => {
+                let bodies: Vec<MajorBody> = MajorBody::ALL
+                    .iter()
+                    .copied()
+                    .filter(|body| {
+                        database
+                            .data
+                            .iter()
+                            .any(|satellite| satellite.major_body == *body)
+                    })
+                    .collect();
+                let background = Rect::new(
+                    right - 110.0,
+                    10.0,
+                    right,
+                    20.0 + bodies.len() as f64 * 20.0,
+                );
+                ctx.fill(background, &Color::BLACK.with_alpha(0.6));
+                for (index, body) in bodies.iter().enumerate() {
+                    let y = 15.0 + index as f64 * 20.0;
+                    let swatch = Rect::new(right - 100.0, y + 3.0, right - 88.0, y + 15.0);
+                    ctx.fill(swatch, &major_body_color(*body));
+                    let text = ctx
+                        .text()
+                        .new_text_layout(body.to_string())
+                        .text_color(Color::WHITE)
+                        .build()
+                        .unwrap();
+                    ctx.draw_text(&text, (right - 80.0, y));
+                }
+            }
+            ColorMapping::ByExpression {
+                query,
+                colormap,
+                min,
+                max,
+            } => {
+                let steps = 100;
+                let height = 200.0;
+                let top = 40.0;
+                let background = Rect::new(right - 110.0, 10.0, right, top + height + 10.0);
+                ctx.fill(background, &Color::BLACK.with_alpha(0.6));
+                for step in 0..steps {
+                    let t = step as f64 / (steps - 1) as f64;
+                    let y = top + height * (1.0 - t);
+                    let rect = Rect::new(right - 100.0, y - height / steps as f64, right - 80.0, y);
+                    ctx.fill(rect, &colormap.color(t));
+                }
+                let title = ctx
+                    .text()
+                    .new_text_layout(query.source().to_string())
+                    .text_color(Color::WHITE)
+                    .build()
+                    .unwrap();
+                ctx.draw_text(&title, (right - 100.0, 15.0));
+                let max_text = ctx
+                    .text()
+                    .new_text_layout(format!("{:.3}", max))
+                    .text_color(Color::WHITE)
+                    .build()
+                    .unwrap();
+                ctx.draw_text(&max_text, (right - 75.0, top - 8.0));
+                let min_text = ctx
+                    .text()
+                    .new_text_layout(format!("{:.3}", min))
+                    .text_color(Color::WHITE)
+                    .build()
+                    .unwrap();
+                ctx.draw_text(&min_text, (right - 75.0, top + height - 8.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{satellite, SatelliteBuilder, UNKNOWN};
+
+    #[test]
+    fn unknown_values_are_grey_and_out_of_range() {
+        let database = Database {
+            data: vec![
+                satellite(0, MajorBody::Saturn, 1000.0).albedo(0.2),
+                satellite(1, MajorBody::Saturn, 1000.0).albedo(UNKNOWN),
+                satellite(2, MajorBody::Saturn, 1000.0).albedo(0.6),
+            ],
+        };
+        let mapping = ColorMapping::by_expression("albedo", Colormap::Viridis, &database);
+        match &mapping {
+            ColorMapping::ByExpression { min, max, .. } => assert_eq!((*min, *max), (0.2, 0.6)),
+            ColorMapping::ByMajorBody => unreachable!(),
+        }
+        let rgba = |index: usize| mapping.color(&database.data[index]).as_rgba_u32();
+        assert_eq!(rgba(1), Color::grey(0.5).as_rgba_u32());
+        assert_eq!(rgba(0), Colormap::Viridis.color(0.0).as_rgba_u32());
+        assert_eq!(rgba(2), Colormap::Viridis.color(1.0).as_rgba_u32());
+    }
+}
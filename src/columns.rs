@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, Selector, Widget};
+use evalexpr::{
+    build_operator_tree, Context, ContextWithMutableVariables, HashMapContext, Node, Value,
+};
+use satellite_data::satellites::Satellite;
+use serde_json::{json, Value as JsonValue};
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+use crate::fields;
+use crate::functions;
+use crate::library::config_directory;
+use crate::query::describe;
+
+pub const ADD_COLUMN: Selector = Selector::new("columns.add");
+// payload is the name of the column
+pub const REMOVE_COLUMN: Selector<String> = Selector::new("columns.remove");
+
+lazy_static! {
+    static ref COLUMNS: Mutex<ColumnSet> = Mutex::new(ColumnSet::default());
+}
+
+fn is_name(name: &str) -> bool {
+    let mut characters = name.chars();
+    characters
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && characters.all(|character| character.is_alphanumeric() || character == '_')
+}
+
+// names of the catalogue fields, their uncertainties and the functions
+fn is_reserved(name: &str) -> bool {
+    let field = name.strip_suffix("_err").unwrap_or(name);
+    fields::field(field).is_some()
+        || functions::function(name).is_some()
+        || name == "true"
+        || name == "false"
+}
+
+// a named expression evaluated once per satellite and then used like a field
+#[derive(Clone, Debug)]
+pub struct ComputedColumn {
+    pub name: String,
+    pub expression: String,
+    tree: Node,
+    variables: Vec<String>,
+}
+
+impl ComputedColumn {
+    // parses `name = expression`
+    pub fn parse(definition: &str) -> Result<ComputedColumn, String> {
+        let split = definition
+            .char_indices()
+            .find(|(index, character)| {
+                *character == '='
+                    && !definition[index + 1..].starts_with('=')
+                    && !definition[..*index].ends_with(['=', '!', '<', '>'])
+            })
+            .map(|(index, _)| index);
+        let (name, expression) = match split {
+            Some(index) => (definition[..index].trim(), definition[index + 1..].trim()),
+            None => return Err("expected name = expression".to_string()),
+        };
+        if !is_name(name) {
+            return Err(format!("\"{}\" is not a valid name", name));
+        }
+        if is_reserved(name) {
+            return Err(format!("{} is already a variable or function", name));
+        }
+        let tree = build_operator_tree(expression)
+            .map_err(|error| format!("{}: {}", name, describe(expression, &error)))?;
+        let mut variables: Vec<String> =
+            tree.iter_variable_identifiers().map(String::from).collect();
+        variables.sort_unstable();
+        variables.dedup();
+        Ok(ComputedColumn {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            tree,
+            variables,
+        })
+    }
+
+    pub fn definition(&self) -> String {
+        format!("{} = {}", self.name, self.expression)
+    }
+}
+
+// the computed columns in definition order, each may use the ones before it; values
+// are cached per satellite id, which is the position in the catalogue and so unique,
+// until the columns are defined again
+#[derive(Default)]
+pub struct ColumnSet {
+    columns: Vec<ComputedColumn>,
+    values: HashMap<usize, Vec<Value>>,
+    generation: u64,
+}
+
+impl ColumnSet {
+    // keeps every definition that compiles, the others are reported one per line
+    pub fn define(&mut self, definitions: &[String]) -> Result<(), String> {
+        let mut columns: Vec<ComputedColumn> = Vec::new();
+        let mut errors = Vec::new();
+        for definition in definitions {
+            match ComputedColumn::parse(definition) {
+                Ok(column) if columns.iter().any(|defined| defined.name == column.name) => {
+                    errors.push(format!("{} is defined twice", column.name))
+                }
+                Ok(column) => columns.push(column),
+                Err(error) => errors.push(error),
+            }
+        }
+        self.columns = columns;
+        self.values.clear();
+        self.generation += 1;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    // adds the values of the columns to a context holding the satellite's fields;
+    // a column that fails to evaluate or uses an unknown value is empty
+    pub fn extend(&mut self, context: &mut HashMapContext, satellite: &Satellite) {
+        if let Some(values) = self.values.get(&satellite.id) {
+            for (column, value) in self.columns.iter().zip(values) {
+                context
+                    .set_value(column.name.clone(), value.clone())
+                    .unwrap();
+            }
+            return;
+        }
+        let mut values = Vec::with_capacity(self.columns.len());
+        for column in &self.columns {
+            let unknown = column
+                .variables
+                .iter()
+                .any(|variable| is_unknown(context, satellite, variable));
+            let value = if unknown {
+                Value::Empty
+            } else {
+                column
+                    .tree
+                    .eval_with_context(context)
+                    .unwrap_or(Value::Empty)
+            };
+            context
+                .set_value(column.name.clone(), value.clone())
+                .unwrap();
+            values.push(value);
+        }
+        self.values.insert(satellite.id, values);
+    }
+}
+
+// a catalogue value marked unknown, which the context holds as 0, or an empty
+// computed column
+pub fn is_unknown(context: &HashMapContext, satellite: &Satellite, name: &str) -> bool {
+    match fields::field(name) {
+        Some(field) => field.is_numeric() && field.number(satellite).is_none(),
+        None => matches!(context.get_value(name), Some(Value::Empty)),
+    }
+}
+
+pub fn define(definitions: &[String]) -> Result<(), String> {
+    COLUMNS.lock().unwrap().define(definitions)
+}
+
+// changes whenever the columns are defined, so cached results can be dropped
+pub fn generation() -> u64 {
+    COLUMNS.lock().unwrap().generation
+}
+
+pub fn extend(context: &mut HashMapContext, satellite: &Satellite) {
+    COLUMNS.lock().unwrap().extend(context, satellite);
+}
+
+pub fn columns() -> Vec<ComputedColumn> {
+    COLUMNS.lock().unwrap().columns.clone()
+}
+
+pub fn value(name: &str, satellite: &Satellite) -> Value {
+    let context = fields::context(satellite);
+    context_value(&context, name)
+}
+
+fn context_value(context: &HashMapContext, name: &str) -> Value {
+    context.get_value(name).cloned().unwrap_or(Value::Empty)
+}
+
+pub fn number(value: &Value) -> Option<f64> {
+    value.as_number().ok().filter(|number| number.is_finite())
+}
+
+pub fn text(value: &Value) -> String {
+    match value {
+        Value::Empty => "?".to_string(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn session_path() -> Result<PathBuf, String> {
+    Ok(config_directory()?.join("session.json"))
+}
+
+// the column definitions of the last session, none when there was no session
+pub fn load_session() -> Result<Vec<String>, String> {
+    let session: Option<JsonValue> = fs::read_to_string(session_path()?)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok());
+    let columns = session
+        .as_ref()
+        .and_then(|session| session.get("columns"))
+        .and_then(JsonValue::as_array)
+        .map(|columns| {
+            columns
+                .iter()
+                .filter_map(|column| column.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(columns)
+}
+
+fn save_session(definitions: &[String]) -> Result<(), String> {
+    let path = session_path()?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    let session = json!({ "columns": definitions });
+    fs::write(&path, serde_json::to_string_pretty(&session).unwrap())
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn defined_name(definition: &str) -> Option<String> {
+    ComputedColumn::parse(definition)
+        .ok()
+        .map(|column| column.name)
+}
+
+// handles the commands of the column editor, defining the columns again and saving
+// them with the session
+pub struct ColumnEditor;
+
+impl ColumnEditor {
+    fn apply(&self, data: &mut AstronomyCanvasData, definitions: Vec<String>) {
+        data.columns_status = match define(&definitions).and_then(|_| save_session(&definitions)) {
+            Ok(()) => String::new(),
+            Err(error) => error,
+        };
+        data.columns = Arc::new(definitions);
+    }
+}
+
+impl<W: Widget<AstronomyCanvasData>> Controller<AstronomyCanvasData, W> for ColumnEditor {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(command) if command.is(ADD_COLUMN) => {
+                match ComputedColumn::parse(&data.column_definition) {
+                    Ok(column) => {
+                        // a column defined again replaces the old definition in place
+                        let mut definitions = (*data.columns).clone();
+                        let position = definitions.iter().position(|definition| {
+                            defined_name(definition) == Some(column.name.clone())
+                        });
+                        match position {
+                            Some(position) => definitions[position] = column.definition(),
+                            None => definitions.push(column.definition()),
+                        }
+                        self.apply(data, definitions);
+                        data.column_definition = String::new();
+                    }
+                    Err(error) => data.columns_status = error,
+                }
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.is(REMOVE_COLUMN) => {
+                let name = command.get_unchecked(REMOVE_COLUMN);
+                let definitions = data
+                    .columns
+                    .iter()
+                    .filter(|definition| defined_name(definition).as_ref() != Some(name))
+                    .cloned()
+                    .collect();
+                self.apply(data, definitions);
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{satellite, SatelliteBuilder, UNKNOWN};
+    use satellite_data::satellites::MajorBody;
+
+    fn moon(id: usize, major_semiaxis: f64) -> Satellite {
+        satellite(id, MajorBody::Saturn, major_semiaxis)
+            .gm((40.0, 0.0))
+            .radius((100.0, 1.0))
+            .magnitude(UNKNOWN)
+            .albedo(0.5)
+    }
+
+    #[test]
+    fn columns_use_fields_and_earlier_columns() {
+        let mut set = ColumnSet::default();
+        let definitions = vec![
+            "ratio = gm / parent_gm".to_string(),
+            "hill = a * (ratio / 3) ^ (1.0 / 3)".to_string(),
+            "label = name".to_string(),
+        ];
+        set.define(&definitions).unwrap();
+        assert_eq!(set.generation, 1);
+        let satellite = moon(1, 100000.0);
+        let mut context = fields::context(&satellite);
+        set.extend(&mut context, &satellite);
+        let hill = context_value(&context, "hill").as_number().unwrap();
+        let expected = 100000.0 * (40.0 / MajorBody::Saturn.gm() / 3.0).powf(1.0 / 3.0);
+        assert!((hill - expected).abs() < 1e-6);
+        assert_eq!(text(&context_value(&context, "label")), "S/1");
+        assert_eq!(set.values[&1].len(), 3);
+
+        // unknown inputs leave a column, and the columns built on it, empty
+        set.define(&[
+            "bright = magnitude < 10".to_string(),
+            "twice = bright || true".to_string(),
+            "size = radius * 2".to_string(),
+        ])
+        .unwrap();
+        let mut context = fields::context(&satellite);
+        set.extend(&mut context, &satellite);
+        assert_eq!(context_value(&context, "bright"), Value::Empty);
+        assert_eq!(context_value(&context, "twice"), Value::Empty);
+        assert_eq!(number(&context_value(&context, "size")), Some(200.0));
+
+        let error = set
+            .define(&[
+                "a = 2".to_string(),
+                "x == 1".to_string(),
+                "y = (".to_string(),
+                "z = 1".to_string(),
+                "z = 2".to_string(),
+            ])
+            .unwrap_err();
+        assert_eq!(error.lines().count(), 4);
+        assert!(set.values.is_empty());
+        assert_eq!(set.columns.len(), 1);
+        assert_eq!(set.columns[0].definition(), "z = 1");
+    }
+}
@@ -0,0 +1,277 @@
+use druid::kurbo::{Line, Rect};
+use druid::piet::{Color, Text, TextLayoutBuilder};
+use druid::{
+    BoxConstraints, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
+    Point, RenderContext, Size, UpdateCtx, Vec2, Widget,
+};
+use satellite_data::{database::Database, satellites::Satellite};
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+use crate::fields::FIELDS;
+
+const LABEL_WIDTH: f64 = 90.0;
+const CELL_WIDTH: f64 = 170.0;
+const ROW_HEIGHT: f64 = 20.0;
+
+// indices of the smallest and largest known values, None when they are all equal
+pub fn extremes(values: &[Option<f64>]) -> Option<(usize, usize)> {
+    let known = values
+        .iter()
+        .enumerate()
+        .filter_map(|(index, value)| value.filter(|value| value.is_finite()).map(|v| (index, v)));
+    known
+        .fold(None, |extremes, (index, value)| match extremes {
+            None => Some(((index, value), (index, value))),
+            Some((min, max)) => Some((
+                if value < min.1 { (index, value) } else { min },
+                if value > max.1 { (index, value) } else { max },
+            )),
+        })
+        .filter(|(min, max)| min.1 < max.1)
+        .map(|(min, max)| (min.0, max.0))
+}
+
+pub fn ratio(value: Option<f64>, reference: Option<f64>) -> Option<f64> {
+    match (value, reference) {
+        (Some(value), Some(reference)) if reference != 0.0 => Some(value / reference),
+        _ => None,
+    }
+}
+
+// parameters of the selected satellites side by side, with ratios to a reference
+// satellite chosen by clicking its name
+pub struct ComparisonPanel {
+    database: Database,
+    reference: Option<usize>,
+    offset: Vec2,
+}
+
+impl ComparisonPanel {
+    pub fn new(database: Database) -> ComparisonPanel {
+        ComparisonPanel {
+            database,
+            reference: None,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    // the header row and the field labels stay in place while the cells scroll
+    fn clamp_offset(&mut self, size: Size, columns: usize) {
+        let width = LABEL_WIDTH + columns as f64 * CELL_WIDTH;
+        let height = (FIELDS.len() as f64) * ROW_HEIGHT;
+        let max_x = (width - size.width).max(0.0);
+        let max_y = (height - size.height).max(0.0);
+        self.offset.x = self.offset.x.max(0.0).min(max_x);
+        self.offset.y = self.offset.y.max(0.0).min(max_y);
+    }
+
+    fn satellites(&self, data: &AstronomyCanvasData) -> Vec<Satellite> {
+        data.selected_satellites
+            .0
+            .iter()
+            .filter_map(|wrapper| self.database.get_satellite_by_id(wrapper.satellite))
+            .collect()
+    }
+
+    fn reference_index(&self, satellites: &[Satellite]) -> Option<usize> {
+        if satellites.is_empty() {
+            return None;
+        }
+        let index = self
+            .reference
+            .and_then(|id| satellites.iter().position(|satellite| satellite.id == id));
+        Some(index.unwrap_or(0))
+    }
+
+    fn draw_text(&self, ctx: &mut PaintCtx, text: String, color: Color, origin: Point) {
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .text_color(color)
+            .build()
+            .unwrap();
+        ctx.draw_text(&layout, origin);
+    }
+}
+
+impl Widget<AstronomyCanvasData> for ComparisonPanel {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        match event {
+            Event::Wheel(mouse_event) => {
+                let (x, y) = mouse_event.wheel_delta.into();
+                if mouse_event.mods.shift() {
+                    self.offset.x += y;
+                } else {
+                    self.offset += Vec2::new(x, y);
+                }
+                self.clamp_offset(ctx.size(), data.selected_satellites.0.len());
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                let pos = mouse_event.pos;
+                if pos.y < ROW_HEIGHT && pos.x > LABEL_WIDTH {
+                    let index =
+                        ((pos.x - LABEL_WIDTH + self.offset.x) / CELL_WIDTH).floor() as usize;
+                    if let Some(wrapper) = data.selected_satellites.0.get(index) {
+                        self.reference = Some(wrapper.satellite);
+                        ctx.request_paint();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        _event: &LifeCycle,
+        _data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &AstronomyCanvasData,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if !old_data.selected_satellites.same(&data.selected_satellites) {
+            self.clamp_offset(ctx.size(), data.selected_satellites.0.len());
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _layout_ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AstronomyCanvasData,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
+        let size = ctx.size();
+        ctx.clip(size.to_rect());
+        ctx.fill(size.to_rect(), &Color::grey(0.1));
+
+        let satellites = self.satellites(data);
+        let reference = match self.reference_index(&satellites) {
+            Some(reference) => reference,
+            None => {
+                self.draw_text(
+                    ctx,
+                    "select satellites to compare them".to_string(),
+                    Color::grey(0.6),
+                    Point::new(10.0, 10.0),
+                );
+                return;
+            }
+        };
+        let left = |index: usize| LABEL_WIDTH + index as f64 * CELL_WIDTH - self.offset.x;
+
+        let rows = FIELDS.iter().filter(|field| field.name != "name");
+        for (row, field) in rows.enumerate() {
+            let y = (row + 1) as f64 * ROW_HEIGHT - self.offset.y;
+            if y < 0.0 || y > size.height {
+                continue;
+            }
+            if row % 2 == 1 {
+                ctx.fill(
+                    Rect::new(0.0, y, size.width, y + ROW_HEIGHT),
+                    &Color::grey(0.13),
+                );
+            }
+            let values: Vec<Option<f64>> = satellites
+                .iter()
+                .map(|satellite| field.number(satellite))
+                .collect();
+            let extremes = if satellites.len() < 2 {
+                None
+            } else {
+                extremes(&values)
+            };
+            for (index, satellite) in satellites.iter().enumerate() {
+                let x = left(index);
+                if x + CELL_WIDTH < LABEL_WIDTH || x > size.width {
+                    continue;
+                }
+                let cell = Rect::new(x, y, x + CELL_WIDTH, y + ROW_HEIGHT);
+                match extremes {
+                    Some((min, _)) if min == index => {
+                        ctx.fill(cell, &Color::rgb(0.1, 0.3, 0.8).with_alpha(0.5))
+                    }
+                    Some((_, max)) if max == index => {
+                        ctx.fill(cell, &Color::rgb(0.8, 0.3, 0.1).with_alpha(0.5))
+                    }
+                    _ => {}
+                }
+                let mut text = field.text(satellite);
+                if index != reference {
+                    if let Some(ratio) = ratio(values[index], values[reference]) {
+                        text.push_str(&format!(" (×{:.3})", ratio));
+                    }
+                }
+                self.draw_text(ctx, text, Color::WHITE, Point::new(x + 4.0, y + 2.0));
+            }
+            ctx.fill(
+                Rect::new(0.0, y, LABEL_WIDTH, y + ROW_HEIGHT),
+                &Color::grey(0.2),
+            );
+            self.draw_text(
+                ctx,
+                field.name.to_string(),
+                Color::WHITE,
+                Point::new(4.0, y + 2.0),
+            );
+        }
+        ctx.fill(
+            Rect::new(0.0, 0.0, size.width, ROW_HEIGHT),
+            &Color::grey(0.25),
+        );
+        for (index, satellite) in satellites.iter().enumerate() {
+            let mut title = satellite.name.clone();
+            if index == reference {
+                title.push_str(" (ref)");
+            }
+            self.draw_text(ctx, title, Color::WHITE, Point::new(left(index) + 4.0, 2.0));
+        }
+        for index in 0..satellites.len() {
+            let x = left(index);
+            if x >= LABEL_WIDTH {
+                ctx.stroke(
+                    Line::new((x, 0.0), (x, size.height)),
+                    &Color::grey(0.3),
+                    1.0,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extremes_skip_unknown_values() {
+        let values = vec![Some(2.0), None, Some(-1.0), Some(f64::NAN), Some(5.0)];
+        assert_eq!(extremes(&values), Some((2, 4)));
+        assert_eq!(extremes(&[None, None]), None);
+        assert_eq!(extremes(&[Some(1.0), None, Some(1.0)]), None);
+        assert_eq!(ratio(Some(3.0), Some(2.0)), Some(1.5));
+        assert_eq!(ratio(Some(3.0), Some(0.0)), None);
+        assert_eq!(ratio(None, Some(2.0)), None);
+    }
+}
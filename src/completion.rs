@@ -0,0 +1,264 @@
+use std::ops::Range;
+
+use satellite_data::satellites::MajorBody;
+
+use crate::fields::{self, variables};
+use crate::functions::{self, FUNCTIONS};
+
+// most suggestions offered at once
+const MAX_SUGGESTIONS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Variable,
+    Function,
+    Unknown,
+    Literal,
+    Text,
+    Operator,
+}
+
+// operator characters that combine into one token, as in `==` or `&&`
+const PAIRED: &str = "=!<>&|";
+
+// byte ranges of the tokens of `source`, whitespace is skipped
+pub fn tokenize(source: &str) -> Vec<(Range<usize>, TokenKind)> {
+    let characters: Vec<(usize, char)> = source.char_indices().collect();
+    let offset = |index: usize| {
+        characters
+            .get(index)
+            .map_or(source.len(), |(offset, _)| *offset)
+    };
+    let scan = |mut index: usize, predicate: &dyn Fn(char) -> bool| {
+        while index < characters.len() && predicate(characters[index].1) {
+            index += 1;
+        }
+        index
+    };
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < characters.len() {
+        let (start, character) = characters[index];
+        let (next, kind) = if character.is_whitespace() {
+            index += 1;
+            continue;
+        } else if character == '"' {
+            // a string ends at the first quote not escaped with a backslash
+            let mut closing = index + 1;
+            while closing < characters.len() && characters[closing].1 != '"' {
+                closing += if characters[closing].1 == '\\' { 2 } else { 1 };
+            }
+            ((closing + 1).min(characters.len()), TokenKind::Text)
+        } else if character.is_ascii_digit() || character == '.' {
+            let next = scan(index + 1, &|next| {
+                next.is_ascii_alphanumeric() || next == '.'
+            });
+            (next, TokenKind::Literal)
+        } else if is_identifier(character) {
+            let next = scan(index + 1, &is_word);
+            (next, classify(&source[start..offset(next)]))
+        } else if PAIRED.contains(character) {
+            (
+                scan(index + 1, &|next| PAIRED.contains(next)),
+                TokenKind::Operator,
+            )
+        } else {
+            (index + 1, TokenKind::Operator)
+        };
+        tokens.push((start..offset(next), kind));
+        index = next;
+    }
+    tokens
+}
+
+fn is_identifier(character: char) -> bool {
+    character.is_alphabetic() || character == '_'
+}
+
+fn is_word(character: char) -> bool {
+    is_identifier(character) || character.is_ascii_digit() || character == ':'
+}
+
+fn classify(identifier: &str) -> TokenKind {
+    if identifier == "true" || identifier == "false" {
+        TokenKind::Literal
+    } else if functions::function(identifier).is_some() {
+        TokenKind::Function
+    } else if variables()
+        .iter()
+        .any(|variable| variable.name == identifier)
+    {
+        TokenKind::Variable
+    } else {
+        TokenKind::Unknown
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    // replaces the partial word at the end of the expression
+    pub replacement: String,
+    pub label: String,
+    pub help: String,
+}
+
+// the partial word at the end of `source` and whether it is inside a string
+fn partial_word(source: &str) -> (usize, bool) {
+    let quotes = source.matches('"').count();
+    if quotes % 2 == 1 {
+        return (source.rfind('"').unwrap() + 1, true);
+    }
+    let start = source
+        .char_indices()
+        .rev()
+        .take_while(|(_, character)| is_word(*character))
+        .last()
+        .map_or(source.len(), |(index, _)| index);
+    (start, false)
+}
+
+// completions for the word being typed at the end of `source`: variables and
+// functions, or parent body and satellite names inside a string
+pub fn suggestions(source: &str, names: &[String]) -> Vec<Suggestion> {
+    let (start, in_text) = partial_word(source);
+    let prefix = &source[start..];
+    if in_text {
+        let prefix = prefix.to_lowercase();
+        let bodies = MajorBody::ALL
+            .iter()
+            .map(|body| (body.to_string(), "parent body"));
+        let satellites = names.iter().map(|name| (name.clone(), "satellite"));
+        return bodies
+            .chain(satellites)
+            .filter(|(name, _)| name.to_lowercase().starts_with(&prefix))
+            .take(MAX_SUGGESTIONS)
+            .map(|(name, kind)| Suggestion {
+                replacement: format!("{}\"", name),
+                label: name,
+                help: kind.to_string(),
+            })
+            .collect();
+    }
+    if prefix.is_empty() || prefix.starts_with(|character: char| character.is_ascii_digit()) {
+        return Vec::new();
+    }
+    let variables = variables().into_iter().map(|variable| {
+        let mut label = variable.name.clone();
+        if !variable.unit.is_empty() {
+            label.push_str(&format!(" [{}]", variable.unit));
+        }
+        Suggestion {
+            replacement: variable.name,
+            label,
+            help: variable.description,
+        }
+    });
+    let functions = FUNCTIONS.iter().map(|function| Suggestion {
+        replacement: format!("{}(", function.name),
+        label: function.usage.to_string(),
+        help: function.description.to_string(),
+    });
+    variables
+        .chain(functions)
+        .filter(|suggestion| {
+            suggestion.replacement.starts_with(prefix) && suggestion.replacement != prefix
+        })
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+pub fn complete(source: &str, suggestion: &Suggestion) -> String {
+    let (start, _) = partial_word(source);
+    format!("{}{}", &source[..start], suggestion.replacement)
+}
+
+// meaning and unit of the last variable or function named in `source`
+pub fn help(source: &str) -> Option<String> {
+    tokenize(source)
+        .into_iter()
+        .rev()
+        .find_map(|(range, kind)| {
+            let name = &source[range];
+            match kind {
+                TokenKind::Variable => {
+                    let variable = variables()
+                        .into_iter()
+                        .find(|variable| variable.name == name)?;
+                    let mut help = format!("{}: {}", variable.name, variable.description);
+                    if !variable.unit.is_empty() {
+                        help.push_str(&format!(", in {}", variable.unit));
+                    }
+                    if let Some(field) = fields::field(name) {
+                        help.push_str(&format!(" ({})", field.source));
+                    }
+                    Some(help)
+                }
+                TokenKind::Function => {
+                    let function = functions::function(name)?;
+                    Some(format!("{}: {}", function.usage, function.description))
+                }
+                _ => None,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_classified() {
+        let source = "mb == \"Saturn\" && log10(a) > 6.5 || foo";
+        let kinds: Vec<(&str, TokenKind)> = tokenize(source)
+            .into_iter()
+            .map(|(range, kind)| (&source[range], kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("mb", TokenKind::Variable),
+                ("==", TokenKind::Operator),
+                ("\"Saturn\"", TokenKind::Text),
+                ("&&", TokenKind::Operator),
+                ("log10", TokenKind::Function),
+                ("(", TokenKind::Operator),
+                ("a", TokenKind::Variable),
+                (")", TokenKind::Operator),
+                (">", TokenKind::Operator),
+                ("6.5", TokenKind::Literal),
+                ("||", TokenKind::Operator),
+                ("foo", TokenKind::Unknown),
+            ]
+        );
+        let escaped = "name == \"a \\\"b\\\" \\\\\" && i";
+        let tokens = tokenize(escaped);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(&escaped[tokens[2].0.clone()], "\"a \\\"b\\\" \\\\\"");
+    }
+
+    #[test]
+    fn completes_the_last_word() {
+        let names = vec!["Pan".to_string(), "Pandora".to_string(), "Io".to_string()];
+        let parent = suggestions("radius > 10 && parent_r", &names);
+        assert_eq!(parent[0].replacement, "parent_radius");
+        assert_eq!(
+            complete("radius > 10 && parent_r", &parent[0]),
+            "radius > 10 && parent_radius"
+        );
+        let labels: Vec<String> = suggestions("name == \"pa", &names)
+            .into_iter()
+            .map(|suggestion| suggestion.label)
+            .collect();
+        assert_eq!(labels, vec!["Pan", "Pandora"]);
+        assert_eq!(
+            complete("mb == \"Sat", &suggestions("mb == \"Sat", &names)[0]),
+            "mb == \"Saturn\""
+        );
+        assert!(suggestions("a > 1", &names).is_empty());
+        assert_eq!(suggestions("sq", &names)[0].replacement, "sqrt(");
+        assert_eq!(
+            help("a > 1 && radius").unwrap(),
+            "radius: Mean radius, in km (JPL SSD planetary satellite physical parameters)"
+        );
+    }
+}
@@ -0,0 +1,651 @@
+use std::f64::consts::PI;
+
+use evalexpr::{Context, ContextWithMutableVariables, HashMapContext, Value};
+use satellite_data::{
+    data::data_with_error::DataWithError,
+    satellites::{MajorBody, Satellite},
+};
+use serde_json::{json, Map};
+
+use crate::columns;
+use crate::functions;
+
+// gravitational constant in km³/(kg·s²)
+const GRAVITATIONAL_CONSTANT: f64 = 6.674_30e-20;
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+const ORBITS_SOURCE: &str = "JPL SSD planetary satellite mean elements";
+const PHYSICAL_SOURCE: &str = "JPL SSD planetary satellite physical parameters";
+const PLANETS_SOURCE: &str = "JPL SSD planetary physical parameters";
+const GROUPS_SOURCE: &str = "classified by a and i, after the groups named by Sheppard et al.";
+// moons beyond this many parent radii are counted as irregular
+const IRREGULAR_RADII: f64 = 100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldGroup {
+    Identity,
+    Orbital,
+    Physical,
+    Parent,
+    Derived,
+    // user-defined, see `columns`
+    Computed,
+}
+
+pub const GROUPS: [FieldGroup; 6] = [
+    FieldGroup::Identity,
+    FieldGroup::Orbital,
+    FieldGroup::Physical,
+    FieldGroup::Parent,
+    FieldGroup::Derived,
+    FieldGroup::Computed,
+];
+
+impl FieldGroup {
+    pub fn title(&self) -> &'static str {
+        match self {
+            FieldGroup::Identity => "Identity",
+            FieldGroup::Orbital => "Orbital",
+            FieldGroup::Physical => "Physical",
+            FieldGroup::Parent => "Parent body",
+            FieldGroup::Derived => "Derived",
+            FieldGroup::Computed => "Computed",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Plain,
+    Fixed(usize),
+    Scientific(usize),
+}
+
+impl Format {
+    pub fn number(&self, value: f64) -> String {
+        match self {
+            Format::Plain => value.to_string(),
+            Format::Fixed(decimals) => format!("{:.*}", decimals, value),
+            Format::Scientific(decimals) => format!("{:.*e}", decimals, value),
+        }
+    }
+}
+
+// measured values keep the catalogue convention: a negative error marks an unknown
+// value and a zero error a value reported without uncertainty
+#[derive(Clone, Debug)]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Measured(DataWithError<f64>),
+}
+
+pub struct Field {
+    // identifier used in filter and axis expressions
+    pub name: &'static str,
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub format: Format,
+    pub group: FieldGroup,
+    pub source: &'static str,
+    pub get: fn(&Satellite) -> FieldValue,
+}
+
+fn scaled(value: DataWithError<f64>, factor: f64) -> DataWithError<f64> {
+    if value.error < 0.0 {
+        value
+    } else {
+        DataWithError {
+            data: value.data * factor,
+            error: value.error * factor.abs(),
+        }
+    }
+}
+
+pub const FIELDS: &[Field] = &[
+    Field {
+        name: "name",
+        label: "Name",
+        unit: "",
+        format: Format::Plain,
+        group: FieldGroup::Identity,
+        source: ORBITS_SOURCE,
+        get: |satellite| FieldValue::Text(satellite.name.clone()),
+    },
+    Field {
+        name: "mb",
+        label: "Major body",
+        unit: "",
+        format: Format::Plain,
+        group: FieldGroup::Identity,
+        source: ORBITS_SOURCE,
+        get: |satellite| FieldValue::Text(satellite.major_body.to_string()),
+    },
+    Field {
+        name: "group",
+        label: "Group",
+        unit: "",
+        format: Format::Plain,
+        group: FieldGroup::Identity,
+        source: GROUPS_SOURCE,
+        get: |satellite| FieldValue::Text(orbital_group(satellite).to_string()),
+    },
+    Field {
+        name: "a",
+        label: "Semi-major axis",
+        unit: "km",
+        format: Format::Plain,
+        group: FieldGroup::Orbital,
+        source: ORBITS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.orbital_params.major_semiaxis),
+    },
+    Field {
+        name: "e",
+        label: "Eccentricity",
+        unit: "",
+        format: Format::Plain,
+        group: FieldGroup::Orbital,
+        source: ORBITS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.orbital_params.eccentricity),
+    },
+    Field {
+        name: "i",
+        label: "Inclination",
+        unit: "deg",
+        format: Format::Plain,
+        group: FieldGroup::Orbital,
+        source: ORBITS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.orbital_params.inclination),
+    },
+    Field {
+        name: "node",
+        label: "Ascending node",
+        unit: "deg",
+        format: Format::Plain,
+        group: FieldGroup::Orbital,
+        source: ORBITS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.orbital_params.ascending_node),
+    },
+    Field {
+        name: "gm",
+        label: "GM",
+        unit: "km³/s²",
+        format: Format::Plain,
+        group: FieldGroup::Physical,
+        source: PHYSICAL_SOURCE,
+        get: |satellite| FieldValue::Measured(satellite.physical_params.gm),
+    },
+    Field {
+        name: "radius",
+        label: "Mean radius",
+        unit: "km",
+        format: Format::Plain,
+        group: FieldGroup::Physical,
+        source: PHYSICAL_SOURCE,
+        get: |satellite| FieldValue::Measured(satellite.physical_params.radius),
+    },
+    Field {
+        name: "density",
+        label: "Density",
+        unit: "g/cm³",
+        format: Format::Plain,
+        group: FieldGroup::Physical,
+        source: PHYSICAL_SOURCE,
+        get: |satellite| FieldValue::Measured(satellite.physical_params.density),
+    },
+    Field {
+        name: "magnitude",
+        label: "Magnitude",
+        unit: "",
+        format: Format::Plain,
+        group: FieldGroup::Physical,
+        source: PHYSICAL_SOURCE,
+        get: |satellite| FieldValue::Measured(satellite.physical_params.magnitude),
+    },
+    Field {
+        name: "albedo",
+        label: "Geometric albedo",
+        unit: "",
+        format: Format::Plain,
+        group: FieldGroup::Physical,
+        source: PHYSICAL_SOURCE,
+        get: |satellite| FieldValue::Measured(satellite.physical_params.albedo),
+    },
+    Field {
+        name: "parent_radius",
+        label: "Parent equatorial radius",
+        unit: "km",
+        format: Format::Plain,
+        group: FieldGroup::Parent,
+        source: PLANETS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.major_body.radius()),
+    },
+    Field {
+        name: "parent_gm",
+        label: "Parent GM",
+        unit: "km³/s²",
+        format: Format::Plain,
+        group: FieldGroup::Parent,
+        source: PLANETS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.major_body.gm()),
+    },
+    Field {
+        name: "parent_mass",
+        label: "Parent mass",
+        unit: "kg",
+        format: Format::Scientific(3),
+        group: FieldGroup::Parent,
+        source: PLANETS_SOURCE,
+        get: |satellite| FieldValue::Number(satellite.major_body.gm() / GRAVITATIONAL_CONSTANT),
+    },
+    Field {
+        name: "periapsis",
+        label: "Periapsis",
+        unit: "km",
+        format: Format::Fixed(1),
+        group: FieldGroup::Derived,
+        source: "a(1 - e)",
+        get: |satellite| {
+            let orbit = &satellite.orbital_params;
+            FieldValue::Number(orbit.major_semiaxis * (1.0 - orbit.eccentricity))
+        },
+    },
+    Field {
+        name: "apoapsis",
+        label: "Apoapsis",
+        unit: "km",
+        format: Format::Fixed(1),
+        group: FieldGroup::Derived,
+        source: "a(1 + e)",
+        get: |satellite| {
+            let orbit = &satellite.orbital_params;
+            FieldValue::Number(orbit.major_semiaxis * (1.0 + orbit.eccentricity))
+        },
+    },
+    Field {
+        name: "diameter",
+        label: "Diameter",
+        unit: "km",
+        format: Format::Plain,
+        group: FieldGroup::Derived,
+        source: "2 radius",
+        get: |satellite| FieldValue::Measured(scaled(satellite.physical_params.radius, 2.0)),
+    },
+    Field {
+        name: "mass",
+        label: "Mass",
+        unit: "kg",
+        format: Format::Scientific(3),
+        group: FieldGroup::Derived,
+        source: "gm / G",
+        get: |satellite| {
+            FieldValue::Measured(scaled(
+                satellite.physical_params.gm,
+                1.0 / GRAVITATIONAL_CONSTANT,
+            ))
+        },
+    },
+    Field {
+        name: "gravity",
+        label: "Surface gravity",
+        unit: "m/s²",
+        format: Format::Fixed(4),
+        group: FieldGroup::Derived,
+        source: "gm / radius²",
+        get: |satellite| {
+            let gm = satellite.physical_params.gm;
+            let radius = satellite.physical_params.radius;
+            if gm.error < 0.0 || radius.error < 0.0 || radius.data <= 0.0 {
+                return FieldValue::Measured(DataWithError {
+                    data: 0.0,
+                    error: -1.0,
+                });
+            }
+            let data = gm.data / radius.data.powi(2) * 1000.0;
+            let relative = if gm.data != 0.0 {
+                (gm.error / gm.data).hypot(2.0 * radius.error / radius.data)
+            } else {
+                0.0
+            };
+            FieldValue::Measured(DataWithError {
+                data,
+                error: (data * relative).abs(),
+            })
+        },
+    },
+    Field {
+        name: "period",
+        label: "Orbital period",
+        unit: "days",
+        format: Format::Fixed(4),
+        group: FieldGroup::Derived,
+        source: "2π √(a³ / (parent_gm + gm))",
+        get: |satellite| {
+            let a = satellite.orbital_params.major_semiaxis;
+            let gm = satellite.major_body.gm() + satellite.physical_params.gm.data.max(0.0);
+            FieldValue::Number(2.0 * PI * (a.powi(3) / gm).sqrt() / SECONDS_PER_DAY)
+        },
+    },
+    Field {
+        name: "a_radii",
+        label: "Semi-major axis in parent radii",
+        unit: "",
+        format: Format::Fixed(3),
+        group: FieldGroup::Derived,
+        source: "a / parent_radius",
+        get: |satellite| {
+            FieldValue::Number(
+                satellite.orbital_params.major_semiaxis / satellite.major_body.radius(),
+            )
+        },
+    },
+];
+
+// the family of irregular moons of Jupiter and Saturn an orbit falls in, otherwise
+// whether the moon is regular or irregular
+pub fn orbital_group(satellite: &Satellite) -> &'static str {
+    let a = satellite.orbital_params.major_semiaxis;
+    let i = satellite.orbital_params.inclination;
+    if a < IRREGULAR_RADII * satellite.major_body.radius() {
+        return "Regular";
+    }
+    match satellite.major_body {
+        MajorBody::Saturn if i > 90.0 => "Norse",
+        MajorBody::Saturn if (40.0..55.0).contains(&i) => "Inuit",
+        MajorBody::Saturn if (30.0..40.0).contains(&i) => "Gallic",
+        MajorBody::Jupiter if (25.0..32.0).contains(&i) && a < 1.3e7 => "Himalia",
+        MajorBody::Jupiter if i >= 160.0 => "Carme",
+        MajorBody::Jupiter if i > 90.0 && a < 2.2e7 => "Ananke",
+        MajorBody::Jupiter if i > 90.0 => "Pasiphae",
+        _ => "Irregular",
+    }
+}
+
+// a variable of the expression context, every numeric field also has an `_err` one
+pub struct Variable {
+    pub name: String,
+    pub unit: &'static str,
+    pub description: String,
+    pub group: FieldGroup,
+}
+
+pub fn variables() -> Vec<Variable> {
+    let mut variables = Vec::new();
+    for field in FIELDS {
+        variables.push(Variable {
+            name: field.name.to_string(),
+            unit: field.unit,
+            description: field.label.to_string(),
+            group: field.group,
+        });
+        if field.is_numeric() {
+            variables.push(Variable {
+                name: format!("{}_err", field.name),
+                unit: field.unit,
+                description: format!(
+                    "Uncertainty of {}, 0 when not reported and negative when unknown",
+                    field.label.to_lowercase()
+                ),
+                group: field.group,
+            });
+        }
+    }
+    for column in columns::columns() {
+        variables.push(Variable {
+            name: column.name,
+            unit: "",
+            description: column.expression,
+            group: FieldGroup::Computed,
+        });
+    }
+    variables
+}
+
+// every variable with its unit and meaning, grouped as in the detail panel
+pub fn variables_text() -> String {
+    let variables = variables();
+    let mut text = String::new();
+    for group in GROUPS.iter() {
+        text.push_str(group.title());
+        text.push('\n');
+        for variable in variables.iter().filter(|variable| variable.group == *group) {
+            text.push_str(&variable.name);
+            if !variable.unit.is_empty() {
+                text.push_str(&format!(" [{}]", variable.unit));
+            }
+            text.push_str(&format!("\t{}\n", variable.description));
+        }
+    }
+    text
+}
+
+pub fn field(name: &str) -> Option<&'static Field> {
+    FIELDS.iter().find(|field| field.name == name)
+}
+
+impl Field {
+    pub fn value(&self, satellite: &Satellite) -> FieldValue {
+        (self.get)(satellite)
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.group != FieldGroup::Identity
+    }
+
+    // None for text fields and values the catalogue marks as unknown
+    pub fn number(&self, satellite: &Satellite) -> Option<f64> {
+        match self.value(satellite) {
+            FieldValue::Text(_) => None,
+            FieldValue::Number(value) => Some(value),
+            FieldValue::Measured(value) if value.error < 0.0 => None,
+            FieldValue::Measured(value) => Some(value.data),
+        }
+    }
+
+    pub fn measured(&self, satellite: &Satellite) -> Option<DataWithError<f64>> {
+        match self.value(satellite) {
+            FieldValue::Measured(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn relative_error(&self, satellite: &Satellite) -> Option<f64> {
+        self.measured(satellite)
+            .filter(|value| value.error > 0.0 && value.data != 0.0)
+            .map(|value| value.error / value.data.abs())
+    }
+
+    // value with its uncertainty, without the unit
+    pub fn text(&self, satellite: &Satellite) -> String {
+        match self.value(satellite) {
+            FieldValue::Text(text) => text,
+            FieldValue::Number(value) => self.format.number(value),
+            FieldValue::Measured(value) if value.error < 0.0 => "?".to_string(),
+            FieldValue::Measured(value) if value.error > 0.0 => format!(
+                "{}±{}",
+                self.format.number(value.data),
+                self.format.number(value.error)
+            ),
+            FieldValue::Measured(value) => self.format.number(value.data),
+        }
+    }
+
+    pub fn describe(&self, satellite: &Satellite) -> String {
+        let mut text = format!("{}:\t{}", self.label, self.text(satellite));
+        if !self.unit.is_empty() {
+            text.push(' ');
+            text.push_str(self.unit);
+        }
+        if let Some(relative) = self.relative_error(satellite) {
+            text.push_str(&format!(" ({:.1}%)", relative * 100.0));
+        }
+        text
+    }
+}
+
+// evaluation context with every variable and function, unknown measured values are
+// 0 and their uncertainty negative as in the catalogue
+pub fn context(satellite: &Satellite) -> HashMapContext {
+    let mut context = catalogue_context(satellite);
+    columns::extend(&mut context, satellite);
+    context
+}
+
+// the context without the computed columns
+fn catalogue_context(satellite: &Satellite) -> HashMapContext {
+    let mut context = HashMapContext::new();
+    functions::register(&mut context);
+    for field in FIELDS {
+        let (value, error) = match field.value(satellite) {
+            FieldValue::Text(text) => (Value::String(text), None),
+            FieldValue::Number(value) => (Value::Float(value), Some(0.0)),
+            FieldValue::Measured(value) => (Value::Float(value.data), Some(value.error)),
+        };
+        context.set_value(field.name.to_string(), value).unwrap();
+        if let Some(error) = error {
+            context
+                .set_value(format!("{}_err", field.name), Value::Float(error))
+                .unwrap();
+        }
+    }
+    context
+}
+
+pub fn record_text(satellite: &Satellite) -> String {
+    let mut text = String::new();
+    for group in GROUPS
+        .iter()
+        .filter(|group| **group != FieldGroup::Computed)
+    {
+        let fields: Vec<&Field> = FIELDS
+            .iter()
+            .filter(|field| field.group == *group)
+            .collect();
+        text.push_str(group.title());
+        if *group != FieldGroup::Derived {
+            let mut sources: Vec<&str> = fields.iter().map(|field| field.source).collect();
+            sources.dedup();
+            text.push_str(&format!(" ({})", sources.join(", ")));
+        }
+        text.push('\n');
+        for field in fields {
+            text.push_str(&field.describe(satellite));
+            if field.group == FieldGroup::Derived {
+                text.push_str(&format!("\t= {}", field.source));
+            }
+            text.push('\n');
+        }
+    }
+    let computed = columns::columns();
+    if !computed.is_empty() {
+        let context = context(satellite);
+        text.push_str(FieldGroup::Computed.title());
+        text.push('\n');
+        for column in computed {
+            let value = context.get_value(&column.name).unwrap_or(&Value::Empty);
+            text.push_str(&format!(
+                "{}:\t{}\t= {}\n",
+                column.name,
+                columns::text(value),
+                column.expression
+            ));
+        }
+    }
+    text
+}
+
+pub fn record_json(satellite: &Satellite) -> String {
+    let mut record = Map::new();
+    for field in FIELDS {
+        let value = match field.value(satellite) {
+            FieldValue::Text(text) => json!(text),
+            FieldValue::Number(value) => json!(value),
+            FieldValue::Measured(value) if value.error < 0.0 => json!(null),
+            FieldValue::Measured(value) => json!({
+                "value": value.data,
+                "error": value.error,
+                "unit": field.unit,
+            }),
+        };
+        record.insert(field.name.to_string(), value);
+    }
+    let computed = columns::columns();
+    if !computed.is_empty() {
+        let context = context(satellite);
+        for column in computed {
+            let value = match context.get_value(&column.name) {
+                Some(Value::String(text)) => json!(text),
+                Some(Value::Boolean(value)) => json!(value),
+                Some(value) => json!(columns::number(value)),
+                None => json!(null),
+            };
+            record.insert(column.name, value);
+        }
+    }
+    serde_json::to_string_pretty(&record).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, SatelliteBuilder, UNKNOWN};
+
+    fn satellite() -> Satellite {
+        test_support::satellite(0, MajorBody::Jupiter, 421800.0)
+            .named("Io")
+            .eccentricity(0.004)
+            .gm((5959.916, 0.012))
+            .radius((1821.49, 0.5))
+            .density(3.528)
+            .magnitude(UNKNOWN)
+            .albedo((0.63, 0.02))
+    }
+
+    #[test]
+    fn derived_values_and_formatting() {
+        let io = satellite();
+        let gravity = field("gravity").unwrap().number(&io).unwrap();
+        assert!((gravity - 1.796).abs() < 1e-3);
+        assert_eq!(field("periapsis").unwrap().text(&io), "420112.8");
+        assert_eq!(field("diameter").unwrap().text(&io), "3642.98±1");
+        assert_eq!(field("magnitude").unwrap().number(&io), None);
+        assert_eq!(field("density").unwrap().relative_error(&io), None);
+        let period = field("period").unwrap().number(&io).unwrap();
+        assert!((period - 1.769).abs() < 1e-3);
+        let albedo = field("albedo").unwrap().describe(&io);
+        assert_eq!(albedo, "Geometric albedo:\t0.63±0.02 (3.2%)");
+        assert_eq!(field("group").unwrap().text(&io), "Regular");
+        let mut phoebe = satellite();
+        phoebe.major_body = MajorBody::Saturn;
+        phoebe.orbital_params.major_semiaxis = 12947918.0;
+        phoebe.orbital_params.inclination = 175.2;
+        assert_eq!(orbital_group(&phoebe), "Norse");
+    }
+
+    #[test]
+    fn context_has_every_variable() {
+        let context = context(&satellite());
+        assert_eq!(context.get_value("radius_err"), Some(&Value::Float(0.5)));
+        assert_eq!(
+            context.get_value("magnitude_err"),
+            Some(&Value::Float(-1.0))
+        );
+        assert_eq!(context.get_value("a_err"), Some(&Value::Float(0.0)));
+        assert_eq!(
+            context.get_value("parent_radius"),
+            Some(&Value::Float(71492.0))
+        );
+        assert!(context.get_value("name_err").is_none());
+        assert!(variables()
+            .iter()
+            .all(|variable| context.get_value(&variable.name).is_some()));
+    }
+
+    #[test]
+    fn json_record_marks_unknown_values() {
+        let record: serde_json::Value = serde_json::from_str(&record_json(&satellite())).unwrap();
+        assert_eq!(record["name"], "Io");
+        assert!(record["magnitude"].is_null());
+        assert_eq!(record["radius"]["error"], 0.5);
+    }
+}
@@ -0,0 +1,626 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use druid::widget::Controller;
+use druid::{lens, Data, Env, Event, EventCtx, Lens, Selector, Widget};
+use satellite_data::{database::Database, satellites::Satellite};
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+use crate::columns;
+use crate::completion::{tokenize, TokenKind};
+use crate::fields;
+
+pub const ADD_CONDITION: Selector = Selector::new("filter_builder.add");
+// payload is the id of the condition
+pub const REMOVE_CONDITION: Selector<usize> = Selector::new("filter_builder.remove");
+pub const FROM_TEXT: Selector = Selector::new("filter_builder.from_text");
+
+// significant digits of the bounds picked with the range sliders
+const SLIDER_DIGITS: i32 = 3;
+
+#[derive(Clone, Copy, Data, PartialEq, Debug)]
+pub enum Operator {
+    Less,
+    AtMost,
+    Greater,
+    AtLeast,
+    Equal,
+    NotEqual,
+    Between,
+    Matches,
+    StartsWith,
+    Contains,
+}
+
+// offered for numeric fields and for text fields, in the order the button cycles
+const NUMERIC_OPERATORS: &[Operator] = &[
+    Operator::Between,
+    Operator::Less,
+    Operator::AtMost,
+    Operator::Greater,
+    Operator::AtLeast,
+    Operator::Equal,
+    Operator::NotEqual,
+];
+const TEXT_OPERATORS: &[Operator] = &[
+    Operator::Equal,
+    Operator::NotEqual,
+    Operator::Matches,
+    Operator::StartsWith,
+    Operator::Contains,
+];
+const COMPARISONS: &[Operator] = &[
+    Operator::Less,
+    Operator::AtMost,
+    Operator::Greater,
+    Operator::AtLeast,
+    Operator::Equal,
+    Operator::NotEqual,
+];
+
+impl Operator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Less => "<",
+            Operator::AtMost => "<=",
+            Operator::Greater => ">",
+            Operator::AtLeast => ">=",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Between => "between",
+            Operator::Matches => "matches",
+            Operator::StartsWith => "starts with",
+            Operator::Contains => "contains",
+        }
+    }
+
+    // the expression function of the text tests
+    fn function(&self) -> Option<&'static str> {
+        match self {
+            Operator::Matches => Some("matches"),
+            Operator::StartsWith => Some("starts_with"),
+            Operator::Contains => Some("contains"),
+            _ => None,
+        }
+    }
+
+    fn offered(numeric: bool) -> &'static [Operator] {
+        if numeric {
+            NUMERIC_OPERATORS
+        } else {
+            TEXT_OPERATORS
+        }
+    }
+
+    pub fn next(&self, numeric: bool) -> Operator {
+        let offered = Operator::offered(numeric);
+        match offered.iter().position(|operator| operator == self) {
+            Some(position) => offered[(position + 1) % offered.len()],
+            None => offered[0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Data, PartialEq, Debug)]
+pub enum Join {
+    And,
+    Or,
+}
+
+impl Join {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Join::And => "&&",
+            Join::Or => "||",
+        }
+    }
+}
+
+// one row of the filter builder: a field, an operator and a value, or a range of
+// the field picked with sliders
+#[derive(Clone, Data, Lens, PartialEq, Debug)]
+pub struct Condition {
+    pub id: usize,
+    pub field: String,
+    pub operator: Operator,
+    pub value: String,
+    pub numeric: bool,
+    pub low: f64,
+    pub high: f64,
+    // of the field over the loaded database, the sliders span it
+    pub min: f64,
+    pub max: f64,
+}
+
+// the smallest and largest known value of a catalogue field or computed column,
+// None when it has no numeric values
+pub fn field_range(satellites: &[Satellite], name: &str) -> Option<(f64, f64)> {
+    let field = fields::field(name);
+    satellites
+        .iter()
+        .filter_map(|satellite| match field {
+            Some(field) => field.number(satellite),
+            None => columns::number(&columns::value(name, satellite)),
+        })
+        .filter(|value| value.is_finite())
+        .fold(None, |range, value| match range {
+            Some((min, max)) => Some((value.min(min), value.max(max))),
+            None => Some((value, value)),
+        })
+}
+
+fn round_significant(value: f64, digits: i32) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let decimals = digits - 1 - value.abs().log10().floor() as i32;
+    if decimals >= 0 {
+        let scale = 10f64.powi(decimals);
+        (value * scale).round() / scale
+    } else {
+        let scale = 10f64.powi(-decimals);
+        (value / scale).round() * scale
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::new();
+    let mut characters = inner.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => unquoted.extend(characters.next()),
+            character => unquoted.push(character),
+        }
+    }
+    Some(unquoted)
+}
+
+impl Condition {
+    pub fn new(id: usize, field: &str, satellites: &[Satellite]) -> Condition {
+        let mut condition = Condition {
+            id,
+            field: field.to_string(),
+            operator: Operator::Between,
+            value: String::new(),
+            numeric: true,
+            low: 0.0,
+            high: 0.0,
+            min: 0.0,
+            max: 0.0,
+        };
+        condition.set_field(satellites);
+        condition
+    }
+
+    // takes the range of the field and an operator it offers, after the field changed
+    pub fn set_field(&mut self, satellites: &[Satellite]) {
+        let range = field_range(satellites, self.field.trim());
+        let (min, max) = range.unwrap_or((0.0, 0.0));
+        let numeric = range.is_some();
+        self.min = min;
+        self.max = max;
+        self.low = min;
+        self.high = max;
+        if numeric != self.numeric || !Operator::offered(numeric).contains(&self.operator) {
+            self.operator = Operator::offered(numeric)[0];
+        }
+        self.numeric = numeric;
+    }
+
+    // the slider moves in decades when the field spans more than two of them
+    fn logarithmic(&self) -> bool {
+        self.min > 0.0 && self.max > 100.0 * self.min
+    }
+
+    pub fn fraction(&self, value: f64) -> f64 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        let fraction = if self.logarithmic() {
+            (value / self.min).ln() / (self.max / self.min).ln()
+        } else {
+            (value - self.min) / (self.max - self.min)
+        };
+        fraction.clamp(0.0, 1.0)
+    }
+
+    // the value at a slider position, rounded except at the ends so the extreme
+    // satellites stay in the range
+    pub fn at(&self, fraction: f64) -> f64 {
+        if fraction <= 0.0 {
+            return self.min;
+        }
+        if fraction >= 1.0 {
+            return self.max;
+        }
+        let value = if self.logarithmic() {
+            self.min * (self.max / self.min).powf(fraction)
+        } else {
+            self.min + fraction * (self.max - self.min)
+        };
+        round_significant(value, SLIDER_DIGITS)
+    }
+
+    pub fn unit(&self) -> &'static str {
+        fields::field(self.field.trim()).map_or("", |field| field.unit)
+    }
+
+    // the condition as an expression, None while the field or the value is missing
+    pub fn to_text(&self) -> Option<String> {
+        let field = self.field.trim();
+        let value = self.value.trim();
+        if field.is_empty() {
+            return None;
+        }
+        let text = match self.operator {
+            Operator::Between => {
+                format!("({} >= {} && {} <= {})", field, self.low, field, self.high)
+            }
+            _ if value.is_empty() => return None,
+            operator => match operator.function() {
+                Some(function) => format!("{}({}, {})", function, field, quote(value)),
+                None if self.numeric => format!("{} {} {}", field, operator.symbol(), value),
+                None => format!("{} {} {}", field, operator.symbol(), quote(value)),
+            },
+        };
+        Some(text)
+    }
+}
+
+// the filter expression of the builder, incomplete conditions are left out
+pub fn to_text(conditions: &[Condition], join: Join) -> String {
+    let separator = format!(" {} ", join.symbol());
+    conditions
+        .iter()
+        .filter_map(Condition::to_text)
+        .collect::<Vec<_>>()
+        .join(&separator)
+}
+
+type Token = (Range<usize>, TokenKind);
+
+// the tokens split at `separator` outside parentheses
+fn split<'a>(source: &str, tokens: &'a [Token], separator: &str) -> Vec<&'a [Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, (range, _)) in tokens.iter().enumerate() {
+        match &source[range.clone()] {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            text if text == separator && depth == 0 => {
+                parts.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+// whether the first parenthesis closes at the last token
+fn enclosed(source: &str, tokens: &[Token]) -> bool {
+    let mut depth = 0;
+    for (index, (range, _)) in tokens.iter().enumerate() {
+        match &source[range.clone()] {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return index == tokens.len() - 1 && index > 0;
+        }
+    }
+    false
+}
+
+// `field operator value`, with the value as written
+fn comparison<'a>(source: &'a str, tokens: &[Token]) -> Option<(&'a str, Operator, &'a str)> {
+    match tokens {
+        [(field, TokenKind::Variable), (operator, TokenKind::Operator), value @ ..]
+            if !value.is_empty() =>
+        {
+            let operator = COMPARISONS
+                .iter()
+                .find(|comparison| comparison.symbol() == &source[operator.clone()])?;
+            let value = &source[value[0].0.start..value[value.len() - 1].0.end];
+            Some((&source[field.clone()], *operator, value))
+        }
+        _ => None,
+    }
+}
+
+fn parse_condition(
+    source: &str,
+    tokens: &[Token],
+    satellites: &[Satellite],
+) -> Result<Condition, String> {
+    let written = match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => &source[first.0.start..last.0.end],
+        _ => return Err("empty condition".to_string()),
+    };
+    let unsupported = || format!("\"{}\" cannot be shown as a condition", written);
+    if enclosed(source, tokens) {
+        let inner = &tokens[1..tokens.len() - 1];
+        if let [lower, upper] = split(source, inner, "&&")[..] {
+            let bounds = (comparison(source, lower), comparison(source, upper));
+            if let (Some((field, Operator::AtLeast, low)), Some((other, Operator::AtMost, high))) =
+                bounds
+            {
+                if let (true, Ok(low), Ok(high)) = (field == other, low.parse(), high.parse()) {
+                    let mut condition = Condition::new(0, field, satellites);
+                    if !condition.numeric {
+                        return Err(unsupported());
+                    }
+                    condition.low = low;
+                    condition.high = high;
+                    return Ok(condition);
+                }
+            }
+        }
+        return parse_condition(source, inner, satellites);
+    }
+
+    let text = |index: usize| &source[tokens[index].0.clone()];
+    if let [(function, TokenKind::Function), _, (field, TokenKind::Variable), _, (value, TokenKind::Text), _] =
+        tokens
+    {
+        let operator = TEXT_OPERATORS
+            .iter()
+            .find(|operator| operator.function() == Some(&source[function.clone()]));
+        if let (Some(operator), "(", ",", ")") = (operator, text(1), text(3), text(5)) {
+            let mut condition = Condition::new(0, &source[field.clone()], satellites);
+            condition.operator = *operator;
+            condition.value = unquote(&source[value.clone()]).ok_or_else(unsupported)?;
+            return Ok(condition);
+        }
+    }
+
+    let (field, operator, value) = comparison(source, tokens).ok_or_else(unsupported)?;
+    let mut condition = Condition::new(0, field, satellites);
+    condition.operator = operator;
+    condition.value = if condition.numeric {
+        value.to_string()
+    } else {
+        unquote(value).ok_or_else(unsupported)?
+    };
+    Ok(condition)
+}
+
+// the conditions of a filter joined only by && or only by ||, each a comparison, a
+// range written as `(field >= low && field <= high)` or a text test
+pub fn from_text(source: &str, satellites: &[Satellite]) -> Result<(Vec<Condition>, Join), String> {
+    let tokens = tokenize(source);
+    if tokens.is_empty() {
+        return Ok((Vec::new(), Join::And));
+    }
+    let and = split(source, &tokens, "&&");
+    let or = split(source, &tokens, "||");
+    let (terms, join) = match (and.len() > 1, or.len() > 1) {
+        (true, true) => return Err("the builder cannot mix && and ||".to_string()),
+        (false, true) => (or, Join::Or),
+        _ => (and, Join::And),
+    };
+    let conditions = terms
+        .iter()
+        .enumerate()
+        .map(|(id, term)| {
+            let mut condition = parse_condition(source, term, satellites)?;
+            condition.id = id;
+            Ok(condition)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((conditions, join))
+}
+
+// the rows behind the filter text: the rows given when they wrote it, else the rows
+// read back from it, so a filter typed by hand is not lost to stale rows
+pub fn seed_rows(
+    text: &str,
+    conditions: &[Condition],
+    join: Join,
+    satellites: &[Satellite],
+) -> Result<(Vec<Condition>, Join), String> {
+    if to_text(conditions, join) == text {
+        Ok((conditions.to_vec(), join))
+    } else {
+        from_text(text, satellites)
+    }
+}
+
+// the lenses of the two range sliders, which move between 0 and 1
+pub fn low_fraction() -> impl Lens<Condition, f64> {
+    lens::Map::new(
+        |condition: &Condition| condition.fraction(condition.low),
+        |condition: &mut Condition, fraction: f64| {
+            condition.low = condition.at(fraction).min(condition.high)
+        },
+    )
+}
+
+pub fn high_fraction() -> impl Lens<Condition, f64> {
+    lens::Map::new(
+        |condition: &Condition| condition.fraction(condition.high),
+        |condition: &mut Condition, fraction: f64| {
+            condition.high = condition.at(fraction).max(condition.low)
+        },
+    )
+}
+
+// keeps the filter text in step with the builder rows, and reads the rows back from
+// the text when asked to
+pub struct FilterBuilder {
+    database: Database,
+}
+
+impl FilterBuilder {
+    pub fn new(database: Database) -> FilterBuilder {
+        FilterBuilder { database }
+    }
+}
+
+impl<W: Widget<AstronomyCanvasData>> Controller<AstronomyCanvasData, W> for FilterBuilder {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        env: &Env,
+    ) {
+        let seeded = seed_rows(
+            &data.match_string,
+            &data.filter_conditions,
+            data.filter_join,
+            &self.database.data,
+        );
+        if let Ok((conditions, join)) = &seeded {
+            if *conditions != *data.filter_conditions || *join != data.filter_join {
+                data.filter_conditions = Arc::new(conditions.clone());
+                data.filter_join = *join;
+            }
+        }
+        let before = (data.filter_conditions.clone(), data.filter_join);
+        match event {
+            Event::Command(command) if command.is(FROM_TEXT) => {
+                match from_text(&data.match_string, &self.database.data) {
+                    Ok((conditions, join)) => {
+                        data.filter_conditions = Arc::new(conditions);
+                        data.filter_join = join;
+                        data.builder_status = String::new();
+                    }
+                    Err(error) => data.builder_status = error,
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::Command(command) if command.is(ADD_CONDITION) => {
+                let id = data.filter_conditions.iter().map(|row| row.id + 1).max();
+                let mut conditions = (*data.filter_conditions).clone();
+                conditions.push(Condition::new(id.unwrap_or(0), "", &self.database.data));
+                data.filter_conditions = Arc::new(conditions);
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.is(REMOVE_CONDITION) => {
+                let id = *command.get_unchecked(REMOVE_CONDITION);
+                let mut conditions = (*data.filter_conditions).clone();
+                conditions.retain(|condition| condition.id != id);
+                data.filter_conditions = Arc::new(conditions);
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+        if *before.0 == *data.filter_conditions && before.1 == data.filter_join {
+            return;
+        }
+        if let Err(error) = seeded {
+            data.filter_conditions = before.0;
+            data.filter_join = before.1;
+            data.builder_status = format!("{}, edit the filter text instead", error);
+            return;
+        }
+        // a field typed into a row brings its range and operators
+        let mut conditions = (*data.filter_conditions).clone();
+        for condition in conditions.iter_mut() {
+            let previous = before.0.iter().find(|previous| previous.id == condition.id);
+            if previous.is_some_and(|previous| previous.field != condition.field) {
+                condition.set_field(&self.database.data);
+            }
+        }
+        data.filter_conditions = Arc::new(conditions);
+        data.match_string = to_text(&data.filter_conditions, data.filter_join);
+        data.builder_status = String::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Query;
+    use crate::test_support::{satellite, SatelliteBuilder};
+    use satellite_data::satellites::MajorBody;
+
+    fn moon(id: usize, name: &str, major_semiaxis: f64, inclination: f64) -> Satellite {
+        satellite(id, MajorBody::Saturn, major_semiaxis)
+            .named(name)
+            .eccentricity(0.1)
+            .inclination(inclination)
+    }
+
+    #[test]
+    fn conditions_round_trip_through_text() {
+        let satellites = vec![
+            moon(0, "Mimas", 185539.0, 1.57),
+            moon(1, "Phoebe", 12947918.0, 175.2),
+            moon(2, "S/2004 S 12", 19650000.0, 164.0),
+        ];
+        let mut range = Condition::new(0, "a", &satellites);
+        assert_eq!((range.min, range.max), (185539.0, 19650000.0));
+        assert!(range.logarithmic());
+        range.low = range.at(0.5);
+        assert_eq!(range.low, 1910000.0);
+        assert_eq!(range.at(1.0), 19650000.0);
+
+        let mut name = Condition::new(1, "name", &satellites);
+        assert!(!name.numeric);
+        assert_eq!(name.operator, Operator::Equal);
+        name.operator = name.operator.next(false).next(false);
+        name.value = "^S/20\\d{2}".to_string();
+        let mut inclination = Condition::new(2, "i", &satellites);
+        inclination.operator = Operator::Greater;
+        inclination.value = "90".to_string();
+        let eccentricity = Condition::new(3, "e", &satellites);
+        assert_eq!(eccentricity.operator.next(true), Operator::Less);
+
+        let conditions = vec![range, name, inclination];
+        let text = to_text(&conditions, Join::And);
+        assert_eq!(
+            text,
+            "(a >= 1910000 && a <= 19650000) && matches(name, \"^S/20\\\\d{2}\") && i > 90"
+        );
+        let query = Query::new(&text);
+        assert!(query.matches(&satellites[2]) && !query.matches(&satellites[1]));
+        assert_eq!(
+            from_text(&text, &satellites).unwrap(),
+            (conditions, Join::And)
+        );
+
+        let (parsed, join) = from_text("mb == \"Saturn\" || (e < 0.2)", &satellites).unwrap();
+        assert_eq!(join, Join::Or);
+        assert_eq!(to_text(&parsed, join), "mb == \"Saturn\" || e < 0.2");
+        assert!(from_text("a > 1 && i > 2 || e < 1", &satellites).is_err());
+        assert!(from_text("!(a > 1)", &satellites).is_err());
+        assert!(from_text("name == Io", &satellites).is_err());
+        // quotes and backslashes in a value are escaped and read back
+        let mut quoted = Condition::new(0, "name", &satellites);
+        quoted.value = "say \"moon\" \\".to_string();
+        let text = to_text(&[quoted.clone()], Join::And);
+        assert_eq!(text, "name == \"say \\\"moon\\\" \\\\\"");
+        assert_eq!(from_text(&text, &satellites).unwrap().0, vec![quoted]);
+        assert!(Query::new(&text).matches(&moon(3, "say \"moon\" \\", 1.0, 0.0)));
+        assert_eq!(from_text(" ", &satellites).unwrap().0, Vec::new());
+    }
+
+    #[test]
+    fn typed_filter_survives_adding_a_condition() {
+        let satellites = vec![moon(0, "Mimas", 185539.0, 1.57)];
+        let typed = "mb == \"Saturn\" || e < 0.2";
+        let (mut rows, join) = seed_rows(typed, &[], Join::And, &satellites).unwrap();
+        assert_eq!((rows.len(), join), (2, Join::Or));
+        rows.push(Condition::new(2, "", &satellites));
+        assert_eq!(to_text(&rows, join), typed);
+        // rows that wrote the text are kept, the blank one included
+        assert_eq!(seed_rows(typed, &rows, join, &satellites).unwrap().0, rows);
+        assert!(seed_rows("!(a > 1)", &[], Join::And, &satellites).is_err());
+        assert_eq!(
+            seed_rows("", &[], Join::Or, &satellites).unwrap().1,
+            Join::Or
+        );
+    }
+}
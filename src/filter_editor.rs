@@ -0,0 +1,230 @@
+use druid::kurbo::Rect;
+use druid::piet::{Color, Text, TextAttribute, TextLayoutBuilder};
+use druid::widget::Controller;
+use druid::{
+    BoxConstraints, Env, Event, EventCtx, KbKey, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
+    Point, RenderContext, Size, UpdateCtx, Widget,
+};
+use satellite_data::database::Database;
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+use crate::completion::{self, Suggestion, TokenKind};
+
+const EXPRESSION_HEIGHT: f64 = 40.0;
+const ROW_HEIGHT: f64 = 18.0;
+const HELP_HEIGHT: f64 = 48.0;
+
+fn token_color(kind: TokenKind) -> Color {
+    match kind {
+        TokenKind::Variable => Color::rgb8(0x80, 0xc0, 0xff),
+        TokenKind::Function => Color::rgb8(0xff, 0xd0, 0x60),
+        TokenKind::Unknown => Color::rgb8(0xff, 0x50, 0x50),
+        TokenKind::Literal => Color::rgb8(0x90, 0xe0, 0x90),
+        TokenKind::Text => Color::rgb8(0xe0, 0xa0, 0x70),
+        TokenKind::Operator => Color::grey(0.7),
+    }
+}
+
+fn satellite_names(database: &Database) -> Vec<String> {
+    database
+        .data
+        .iter()
+        .map(|satellite| satellite.name.clone())
+        .collect()
+}
+
+// the filter expression highlighted by token kind, completions for the word
+// being typed, which are applied by clicking them, and help on the last name used
+pub struct FilterAssist {
+    names: Vec<String>,
+    suggestions: Vec<Suggestion>,
+    hovered: Option<usize>,
+}
+
+impl FilterAssist {
+    pub fn new(database: &Database) -> FilterAssist {
+        FilterAssist {
+            names: satellite_names(database),
+            suggestions: Vec::new(),
+            hovered: None,
+        }
+    }
+
+    fn row_at(&self, pos: Point) -> Option<usize> {
+        let row = ((pos.y - EXPRESSION_HEIGHT) / ROW_HEIGHT).floor();
+        if row < 0.0 || row as usize >= self.suggestions.len() {
+            return None;
+        }
+        Some(row as usize)
+    }
+
+    fn draw_text(&self, ctx: &mut PaintCtx, text: String, color: Color, origin: Point, width: f64) {
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .max_width(width)
+            .text_color(color)
+            .build()
+            .unwrap();
+        ctx.draw_text(&layout, origin);
+    }
+}
+
+impl Widget<AstronomyCanvasData> for FilterAssist {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        match event {
+            Event::MouseMove(mouse_event) => {
+                let hovered = self.row_at(mouse_event.pos);
+                if hovered != self.hovered {
+                    self.hovered = hovered;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                if let Some(row) = self.row_at(mouse_event.pos) {
+                    data.match_string =
+                        completion::complete(&data.match_string, &self.suggestions[row]);
+                    ctx.set_handled();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.suggestions = completion::suggestions(&data.match_string, &self.names);
+        }
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &AstronomyCanvasData,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if old_data.match_string != data.match_string {
+            self.suggestions = completion::suggestions(&data.match_string, &self.names);
+            self.hovered = None;
+            ctx.request_layout();
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _layout_ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AstronomyCanvasData,
+        _env: &Env,
+    ) -> Size {
+        let height = EXPRESSION_HEIGHT + self.suggestions.len() as f64 * ROW_HEIGHT + HELP_HEIGHT;
+        bc.constrain(Size::new(bc.max().width, height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
+        let size = ctx.size();
+        ctx.clip(size.to_rect());
+        ctx.fill(size.to_rect(), &Color::grey(0.1));
+
+        let source = data.match_string.clone();
+        let mut builder = ctx
+            .text()
+            .new_text_layout(source.clone())
+            .max_width(size.width - 8.0)
+            .text_color(Color::WHITE);
+        for (range, kind) in completion::tokenize(&source) {
+            if kind == TokenKind::Unknown {
+                builder = builder.range_attribute(range.clone(), TextAttribute::Underline(true));
+            }
+            builder = builder.range_attribute(range, TextAttribute::TextColor(token_color(kind)));
+        }
+        ctx.draw_text(&builder.build().unwrap(), Point::new(4.0, 4.0));
+
+        for (row, suggestion) in self.suggestions.iter().enumerate() {
+            let y = EXPRESSION_HEIGHT + row as f64 * ROW_HEIGHT;
+            if self.hovered == Some(row) {
+                ctx.fill(
+                    Rect::new(0.0, y, size.width, y + ROW_HEIGHT),
+                    &Color::grey(0.25),
+                );
+            }
+            let mut label = suggestion.label.clone();
+            if row == 0 {
+                label.push_str("  (Tab)");
+            }
+            self.draw_text(
+                ctx,
+                label,
+                Color::WHITE,
+                Point::new(4.0, y + 1.0),
+                size.width,
+            );
+        }
+
+        let y = EXPRESSION_HEIGHT + self.suggestions.len() as f64 * ROW_HEIGHT;
+        // the hovered or first suggestion, else the last name in the expression
+        let help = match self.suggestions.get(self.hovered.unwrap_or(0)) {
+            Some(suggestion) => Some(suggestion.help.clone()),
+            None => completion::help(&source),
+        };
+        if let Some(help) = help {
+            self.draw_text(
+                ctx,
+                help,
+                Color::grey(0.6),
+                Point::new(4.0, y + 2.0),
+                size.width - 8.0,
+            );
+        }
+    }
+}
+
+// accepts the first suggestion when Tab is pressed in the filter box
+pub struct CompleteOnTab {
+    names: Vec<String>,
+}
+
+impl CompleteOnTab {
+    pub fn new(database: &Database) -> CompleteOnTab {
+        CompleteOnTab {
+            names: satellite_names(database),
+        }
+    }
+}
+
+impl<W: Widget<AstronomyCanvasData>> Controller<AstronomyCanvasData, W> for CompleteOnTab {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        env: &Env,
+    ) {
+        if let Event::KeyDown(key_event) = event {
+            if key_event.key == KbKey::Tab {
+                let suggestions = completion::suggestions(&data.match_string, &self.names);
+                if let Some(suggestion) = suggestions.first() {
+                    data.match_string = completion::complete(&data.match_string, suggestion);
+                    ctx.set_handled();
+                    return;
+                }
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
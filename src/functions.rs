@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use evalexpr::{ContextWithMutableFunctions, EvalexprError, Function, HashMapContext, Value};
+use regex::Regex;
+
+// most compiled patterns kept; typing a pattern compiles every prefix of it, so the
+// cache starts over when full
+const MAX_PATTERNS: usize = 32;
+
+lazy_static! {
+    // patterns are compiled once, filters call `matches` for every satellite
+    static ref PATTERNS: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+pub enum Implementation {
+    // provided by evalexpr itself
+    Builtin,
+    Number(fn(f64) -> f64),
+    // a test on two pieces of text, such as a name and a pattern
+    TextTest(fn(&str, &str) -> Result<bool, String>),
+}
+
+fn matches(text: &str, pattern: &str) -> Result<bool, String> {
+    let mut patterns = PATTERNS.lock().unwrap();
+    if !patterns.contains_key(pattern) {
+        let regex = Regex::new(pattern).map_err(|error| error.to_string())?;
+        if patterns.len() == MAX_PATTERNS {
+            patterns.clear();
+        }
+        patterns.insert(pattern.to_string(), regex);
+    }
+    Ok(patterns[pattern].is_match(text))
+}
+
+// a function that can be called in filter and axis expressions
+pub struct ExpressionFunction {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub implementation: Implementation,
+}
+
+pub const FUNCTIONS: &[ExpressionFunction] = &[
+    ExpressionFunction {
+        name: "log10",
+        usage: "log10(x)",
+        description: "Base 10 logarithm",
+        implementation: Implementation::Number(f64::log10),
+    },
+    ExpressionFunction {
+        name: "ln",
+        usage: "ln(x)",
+        description: "Natural logarithm",
+        implementation: Implementation::Number(f64::ln),
+    },
+    ExpressionFunction {
+        name: "exp",
+        usage: "exp(x)",
+        description: "e to the power of x",
+        implementation: Implementation::Number(f64::exp),
+    },
+    ExpressionFunction {
+        name: "sqrt",
+        usage: "sqrt(x)",
+        description: "Square root",
+        implementation: Implementation::Number(f64::sqrt),
+    },
+    ExpressionFunction {
+        name: "abs",
+        usage: "abs(x)",
+        description: "Absolute value",
+        implementation: Implementation::Number(f64::abs),
+    },
+    ExpressionFunction {
+        name: "sind",
+        usage: "sind(angle)",
+        description: "Sine of an angle in degrees, as i and node are",
+        implementation: Implementation::Number(|angle| angle.to_radians().sin()),
+    },
+    ExpressionFunction {
+        name: "cosd",
+        usage: "cosd(angle)",
+        description: "Cosine of an angle in degrees, as i and node are",
+        implementation: Implementation::Number(|angle| angle.to_radians().cos()),
+    },
+    ExpressionFunction {
+        name: "matches",
+        usage: "matches(text, pattern)",
+        description: "Whether the regular expression matches part of the text, e.g. matches(name, \"^S/20\") (anchor with ^ and $)",
+        implementation: Implementation::TextTest(matches),
+    },
+    ExpressionFunction {
+        name: "starts_with",
+        usage: "starts_with(text, prefix)",
+        description: "Whether the text starts with the prefix",
+        implementation: Implementation::TextTest(|text, prefix| Ok(text.starts_with(prefix))),
+    },
+    ExpressionFunction {
+        name: "ends_with",
+        usage: "ends_with(text, suffix)",
+        description: "Whether the text ends with the suffix",
+        implementation: Implementation::TextTest(|text, suffix| Ok(text.ends_with(suffix))),
+    },
+    ExpressionFunction {
+        name: "contains",
+        usage: "contains(text, part)",
+        description: "Whether the text contains the part",
+        implementation: Implementation::TextTest(|text, part| Ok(text.contains(part))),
+    },
+    ExpressionFunction {
+        name: "eq_ignore_case",
+        usage: "eq_ignore_case(text, other)",
+        description: "Whether the texts are equal ignoring case",
+        implementation: Implementation::TextTest(|text, other| {
+            Ok(text.to_lowercase() == other.to_lowercase())
+        }),
+    },
+    ExpressionFunction {
+        name: "min",
+        usage: "min(x, y, ...)",
+        description: "Smallest argument",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "max",
+        usage: "max(x, y, ...)",
+        description: "Largest argument",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "floor",
+        usage: "floor(x)",
+        description: "Largest integer not above x",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "round",
+        usage: "round(x)",
+        description: "Nearest integer",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "ceil",
+        usage: "ceil(x)",
+        description: "Smallest integer not below x",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "len",
+        usage: "len(text)",
+        description: "Number of characters",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "str::to_lowercase",
+        usage: "str::to_lowercase(text)",
+        description: "Text in lower case",
+        implementation: Implementation::Builtin,
+    },
+    ExpressionFunction {
+        name: "str::to_uppercase",
+        usage: "str::to_uppercase(text)",
+        description: "Text in upper case",
+        implementation: Implementation::Builtin,
+    },
+];
+
+pub fn function(name: &str) -> Option<&'static ExpressionFunction> {
+    FUNCTIONS.iter().find(|function| function.name == name)
+}
+
+pub fn register(context: &mut HashMapContext) {
+    for function in FUNCTIONS {
+        let function_value = match function.implementation {
+            Implementation::Builtin => continue,
+            Implementation::Number(apply) => Function::new(move |argument: &Value| {
+                Ok(Value::Float(apply(argument.as_number()?)))
+            }),
+            Implementation::TextTest(test) => Function::new(move |argument: &Value| {
+                let arguments = argument.as_fixed_len_tuple(2)?;
+                let (text, other) = (arguments[0].as_string()?, arguments[1].as_string()?);
+                test(&text, &other)
+                    .map(Value::Boolean)
+                    .map_err(EvalexprError::CustomMessage)
+            }),
+        };
+        context
+            .set_function(function.name.to_string(), function_value)
+            .unwrap();
+    }
+}
+
+pub fn functions_text() -> String {
+    let mut text = "Functions\n".to_string();
+    for function in FUNCTIONS {
+        text.push_str(&format!("{}\t{}\n", function.usage, function.description));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evalexpr::{eval_boolean_with_context, eval_number_with_context};
+
+    #[test]
+    fn registered_functions_evaluate() {
+        let mut context = HashMapContext::new();
+        register(&mut context);
+        let value = |expression: &str| eval_number_with_context(expression, &context).unwrap();
+        assert_eq!(value("log10(1000)"), 3.0);
+        assert!((value("cosd(60)") - 0.5).abs() < 1e-12);
+        assert_eq!(value("max(abs(-2), 1)"), 2.0);
+        let test = |expression: &str| eval_boolean_with_context(expression, &context).unwrap();
+        assert!(test("matches(\"S/2004 S 12\", \"^S/20\\\\d{2} S\")"));
+        assert!(!test("matches(\"Siarnaq\", \"^S/20\")"));
+        assert!(test(
+            "starts_with(\"Skathi\", \"Sk\") && eq_ignore_case(\"PHOEBE\", \"Phoebe\")"
+        ));
+        assert!(eval_boolean_with_context("matches(\"Io\", \"(\")", &context).is_err());
+        for length in 0..100 {
+            matches("Io", &"I".repeat(length)).unwrap();
+        }
+        assert!(PATTERNS.lock().unwrap().len() <= MAX_PATTERNS);
+    }
+}
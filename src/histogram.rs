@@ -0,0 +1,176 @@
+use std::f64::consts::PI;
+
+use druid::Data;
+use satellite_data::satellites::{MajorBody, Satellite};
+
+use crate::axis::AxisScale;
+use crate::query::Query;
+
+// most bins a histogram is split into, narrower bin widths are widened to fit
+const MAX_BINS: usize = 1000;
+
+#[derive(Clone, Copy, Data, PartialEq, Debug)]
+pub enum HistogramStyle {
+    Plain,
+    Stacked,
+    Overlaid,
+}
+
+// satellite ids binned by value, `start` and `width` are in the units of the
+// (transformed) values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub start: f64,
+    pub width: f64,
+    pub bins: Vec<Vec<(usize, MajorBody)>>,
+    pub values: Vec<(MajorBody, f64)>,
+}
+
+impl Histogram {
+    // `width` of None picks one bin per square root of the number of values
+    pub fn new(entries: &[(usize, MajorBody, f64)], width: Option<f64>) -> Option<Histogram> {
+        let values: Vec<(usize, MajorBody, f64)> = entries
+            .iter()
+            .copied()
+            .filter(|(_, _, value)| value.is_finite())
+            .collect();
+        let min = values
+            .iter()
+            .map(|entry| entry.2)
+            .fold(f64::INFINITY, f64::min);
+        let max = values
+            .iter()
+            .map(|entry| entry.2)
+            .fold(f64::NEG_INFINITY, f64::max);
+        if values.is_empty() {
+            return None;
+        }
+        let range = max - min;
+        let width = match width.filter(|width| width.is_finite() && *width > 0.0) {
+            Some(width) => width.max(range / MAX_BINS as f64),
+            None if range > 0.0 => range / (values.len() as f64).sqrt().ceil(),
+            None => 1.0,
+        };
+        // bins are aligned to multiples of the width so that they stay put while filtering
+        let start = (min / width).floor() * width;
+        let count = (((max - start) / width).floor() as usize + 1).min(MAX_BINS + 1);
+        let mut bins = vec![Vec::new(); count];
+        for (id, body, value) in &values {
+            let bin = (((value - start) / width).floor() as usize).min(count - 1);
+            bins[bin].push((*id, *body));
+        }
+        Some(Histogram {
+            start,
+            width,
+            bins,
+            values: values
+                .into_iter()
+                .map(|(_, body, value)| (body, value))
+                .collect(),
+        })
+    }
+
+    pub fn end(&self) -> f64 {
+        self.start + self.bins.len() as f64 * self.width
+    }
+
+    pub fn bin_at(&self, value: f64) -> Option<usize> {
+        if value < self.start || value >= self.end() {
+            return None;
+        }
+        Some((((value - self.start) / self.width).floor() as usize).min(self.bins.len() - 1))
+    }
+
+    pub fn count(&self, bin: usize, body: Option<MajorBody>) -> usize {
+        self.bins[bin]
+            .iter()
+            .filter(|(_, satellite_body)| body.map_or(true, |body| body == *satellite_body))
+            .count()
+    }
+
+    pub fn max_count(&self) -> usize {
+        self.bins.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    // gaussian kernel density estimate scaled to satellites per bin, so the curve
+    // lies on top of the bars; the bandwidth is half the bin width
+    pub fn density(&self, value: f64, body: Option<MajorBody>) -> f64 {
+        let bandwidth = self.width / 2.0;
+        let sum: f64 = self
+            .values
+            .iter()
+            .filter(|(satellite_body, _)| body.map_or(true, |body| body == *satellite_body))
+            .map(|(_, sample)| {
+                let z = (value - sample) / bandwidth;
+                (-0.5 * z * z).exp()
+            })
+            .sum();
+        sum / (bandwidth * (2.0 * PI).sqrt()) * self.width
+    }
+}
+
+// the value of every satellite after the axis transform, so a log axis gives
+// logarithmic bins; unknown values are left out rather than counted as 0
+pub fn entries(
+    satellites: &[Satellite],
+    query: &Query,
+    axis: AxisScale,
+) -> Vec<(usize, MajorBody, f64)> {
+    satellites
+        .iter()
+        .filter_map(|satellite| {
+            let value = axis.forward(query.known_value(satellite)?);
+            Some((satellite.id, satellite.major_body, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{satellite, SatelliteBuilder, UNKNOWN};
+
+    #[test]
+    fn bins_split_by_width() {
+        let entries = vec![
+            (0, MajorBody::Jupiter, 10.0),
+            (1, MajorBody::Jupiter, 14.0),
+            (2, MajorBody::Saturn, 150.0),
+            (3, MajorBody::Saturn, 165.0),
+            (4, MajorBody::Saturn, f64::NAN),
+        ];
+        let histogram = Histogram::new(&entries, Some(20.0)).unwrap();
+        assert_eq!(histogram.start, 0.0);
+        assert_eq!(histogram.bins.len(), 9);
+        assert_eq!(histogram.count(0, None), 2);
+        assert_eq!(histogram.count(7, Some(MajorBody::Saturn)), 1);
+        assert_eq!(histogram.count(8, Some(MajorBody::Jupiter)), 0);
+        assert_eq!(histogram.bin_at(165.0), Some(8));
+        assert_eq!(histogram.bin_at(180.0), None);
+        assert_eq!(histogram.max_count(), 2);
+        // the estimate integrates to the number of values, in bins
+        let total: f64 = (-200..400)
+            .map(|x| histogram.density(x as f64, None))
+            .sum::<f64>()
+            / histogram.width;
+        assert!((total - 4.0).abs() < 1e-6);
+        assert!(Histogram::new(&entries[4..], None).is_none());
+    }
+
+    #[test]
+    fn unknown_values_are_not_binned() {
+        let satellites = vec![
+            satellite(0, MajorBody::Saturn, 1000.0).magnitude(12.0),
+            satellite(1, MajorBody::Saturn, 1000.0).magnitude(UNKNOWN),
+            satellite(2, MajorBody::Jupiter, 1000.0).magnitude(14.0),
+        ];
+        let entries = entries(&satellites, &Query::new("magnitude"), AxisScale::Linear);
+        assert_eq!(
+            entries,
+            vec![(0, MajorBody::Saturn, 12.0), (2, MajorBody::Jupiter, 14.0)]
+        );
+        let histogram = Histogram::new(&entries, Some(5.0)).unwrap();
+        assert_eq!(histogram.start, 10.0);
+        assert_eq!(histogram.bins.iter().map(Vec::len).sum::<usize>(), 2);
+    }
+}
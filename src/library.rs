@@ -0,0 +1,313 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use druid::widget::Controller;
+use druid::{Data, Env, Event, EventCtx, Lens, Selector, Widget};
+use serde_json::{json, Value};
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+
+// payloads are the name of the saved query
+pub const LOAD_QUERY: Selector<String> = Selector::new("library.load");
+pub const RENAME_QUERY: Selector<String> = Selector::new("library.rename");
+pub const DELETE_QUERY: Selector<String> = Selector::new("library.delete");
+pub const SAVE_QUERY: Selector = Selector::new("library.save");
+
+// overrides the location of the library, e.g. to point at a shared copy
+const PATH_VARIABLE: &str = "SATELLITE_QUERIES";
+
+#[derive(Clone, Data, Lens, PartialEq, Debug)]
+pub struct SavedQuery {
+    pub name: String,
+    pub filter: String,
+    pub x_value: String,
+    pub y_value: String,
+}
+
+impl SavedQuery {
+    fn new(name: &str, filter: &str, x_value: &str, y_value: &str) -> SavedQuery {
+        SavedQuery {
+            name: name.to_string(),
+            filter: filter.to_string(),
+            x_value: x_value.to_string(),
+            y_value: y_value.to_string(),
+        }
+    }
+}
+
+// offered until the user saves a library of their own
+pub fn default_queries() -> Vec<SavedQuery> {
+    vec![
+        SavedQuery::new(
+            "Regular Saturnian moons",
+            "mb == \"Saturn\" && i < 2 && e < 0.05",
+            "a",
+            "radius",
+        ),
+        SavedQuery::new("Retrograde irregulars", "i > 90", "a", "i"),
+        SavedQuery::new(
+            "Trojans of Tethys",
+            "mb == \"Saturn\" && abs(a - 294619) < 1000",
+            "node",
+            "a",
+        ),
+    ]
+}
+
+// where the library and the session are kept; an error rather than the working
+// directory when no absolute configuration directory is set
+pub fn config_directory() -> Result<PathBuf, String> {
+    config_directory_from(|name| env::var(name).ok())
+}
+
+fn config_directory_from(variable: impl Fn(&str) -> Option<String>) -> Result<PathBuf, String> {
+    let directory = |name: &str| {
+        variable(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    directory("XDG_CONFIG_HOME")
+        .or_else(|| directory("APPDATA"))
+        .or_else(|| directory("HOME").map(|home| home.join(".config")))
+        .map(|config| config.join("natural_satellites_visualiser"))
+        .ok_or_else(|| "no configuration directory, set HOME or XDG_CONFIG_HOME".to_string())
+}
+
+// $SATELLITE_QUERIES, else queries.json in the configuration directory
+pub fn library_path() -> Result<PathBuf, String> {
+    match env::var(PATH_VARIABLE) {
+        Ok(path) => Ok(PathBuf::from(path)),
+        Err(_) => Ok(config_directory()?.join("queries.json")),
+    }
+}
+
+pub fn to_json(queries: &[SavedQuery]) -> String {
+    let records: Vec<Value> = queries
+        .iter()
+        .map(|query| {
+            json!({
+                "name": query.name,
+                "filter": query.filter,
+                "x": query.x_value,
+                "y": query.y_value,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&records).unwrap()
+}
+
+pub fn from_json(text: &str) -> Result<Vec<SavedQuery>, String> {
+    let records: Vec<Value> = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    records
+        .iter()
+        .map(|record| {
+            let text = |key: &str| record.get(key).and_then(Value::as_str).unwrap_or("");
+            if text("name").is_empty() {
+                return Err("saved query without a name".to_string());
+            }
+            Ok(SavedQuery::new(
+                text("name"),
+                text("filter"),
+                text("x"),
+                text("y"),
+            ))
+        })
+        .collect()
+}
+
+// the saved library, the defaults when there is none yet
+pub fn load_library() -> Result<Vec<SavedQuery>, String> {
+    let path = library_path()?;
+    match fs::read_to_string(&path) {
+        Ok(text) => from_json(&text).map_err(|error| format!("{}: {}", path.display(), error)),
+        Err(_) if !path.exists() => Ok(default_queries()),
+        Err(error) => Err(format!("{}: {}", path.display(), error)),
+    }
+}
+
+fn save_library(queries: &[SavedQuery]) -> Result<(), String> {
+    write_library(&library_path()?, queries)
+}
+
+// a library that exists but cannot be read is left alone, so a damaged or shared
+// file is not replaced by the few queries saved since it failed to load
+fn write_library(path: &Path, queries: &[SavedQuery]) -> Result<(), String> {
+    if path.exists() {
+        fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| from_json(&text))
+            .map_err(|error| {
+                format!(
+                    "{}: {}\nnot saved, fix or move the file first",
+                    path.display(),
+                    error
+                )
+            })?;
+    }
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    fs::write(path, to_json(queries)).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// replaces the query of the same name, else appends it
+pub fn insert(queries: &mut Vec<SavedQuery>, query: SavedQuery) {
+    match queries.iter_mut().find(|saved| saved.name == query.name) {
+        Some(saved) => *saved = query,
+        None => queries.push(query),
+    }
+}
+
+pub fn rename(queries: &mut [SavedQuery], name: &str, new_name: &str) -> Result<(), String> {
+    if new_name.trim().is_empty() {
+        return Err("type the new name first".to_string());
+    }
+    if queries.iter().any(|saved| saved.name == new_name) {
+        return Err(format!("\"{}\" already exists", new_name));
+    }
+    if let Some(saved) = queries.iter_mut().find(|saved| saved.name == name) {
+        saved.name = new_name.to_string();
+    }
+    Ok(())
+}
+
+// handles the commands of the library rows, which only see their own query
+pub struct QueryLibrary;
+
+impl QueryLibrary {
+    fn apply(
+        &self,
+        data: &mut AstronomyCanvasData,
+        change: impl FnOnce(&mut Vec<SavedQuery>) -> Result<(), String>,
+    ) {
+        let mut queries = (*data.queries).clone();
+        data.library_status = match change(&mut queries).and_then(|_| save_library(&queries)) {
+            Ok(()) => String::new(),
+            Err(error) => error,
+        };
+        data.queries = Arc::new(queries);
+    }
+}
+
+impl<W: Widget<AstronomyCanvasData>> Controller<AstronomyCanvasData, W> for QueryLibrary {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(command) if command.is(SAVE_QUERY) => {
+                let name = match data.query_name.trim() {
+                    "" => format!("query {}", data.queries.len() + 1),
+                    name => name.to_string(),
+                };
+                let query =
+                    SavedQuery::new(&name, &data.match_string, &data.x_value, &data.y_value);
+                self.apply(data, |queries| {
+                    insert(queries, query);
+                    Ok(())
+                });
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.is(LOAD_QUERY) => {
+                let name = command.get_unchecked(LOAD_QUERY);
+                if let Some(query) = data.queries.iter().find(|saved| &saved.name == name) {
+                    data.match_string = query.filter.clone();
+                    if !query.x_value.is_empty() && !query.y_value.is_empty() {
+                        data.x_value = query.x_value.clone();
+                        data.y_value = query.y_value.clone();
+                        data.graph_view = true;
+                    }
+                    data.query_name = query.name.clone();
+                }
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.is(RENAME_QUERY) => {
+                let name = command.get_unchecked(RENAME_QUERY).clone();
+                let new_name = data.query_name.trim().to_string();
+                self.apply(data, |queries| rename(queries, &name, &new_name));
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.is(DELETE_QUERY) => {
+                let name = command.get_unchecked(DELETE_QUERY).clone();
+                self.apply(data, |queries| {
+                    queries.retain(|saved| saved.name != name);
+                    Ok(())
+                });
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_round_trips_and_edits() {
+        let mut queries = default_queries();
+        assert_eq!(from_json(&to_json(&queries)).unwrap(), queries);
+        assert!(from_json("[{\"filter\": \"a > 1\"}]").is_err());
+
+        insert(
+            &mut queries,
+            SavedQuery::new("Retrograde irregulars", "i > 100", "", ""),
+        );
+        assert_eq!(queries.len(), 3);
+        assert_eq!(queries[1].filter, "i > 100");
+        insert(
+            &mut queries,
+            SavedQuery::new("Large", "radius > 500", "", ""),
+        );
+        assert_eq!(queries.len(), 4);
+
+        assert!(rename(&mut queries, "Large", "Trojans of Tethys").is_err());
+        assert!(rename(&mut queries, "Large", " ").is_err());
+        rename(&mut queries, "Large", "Round moons").unwrap();
+        assert_eq!(queries[3].name, "Round moons");
+    }
+
+    #[test]
+    fn unreadable_library_is_not_overwritten() {
+        let directory = env::temp_dir().join(format!("satellite_queries_{}", std::process::id()));
+        let path = directory.join("queries.json");
+        write_library(&path, &default_queries()).unwrap();
+        write_library(&path, &default_queries()[..1]).unwrap();
+        assert_eq!(
+            from_json(&fs::read_to_string(&path).unwrap())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        fs::write(&path, "[{\"name\": \"shared\", ").unwrap();
+        assert!(write_library(&path, &default_queries()).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[{\"name\": \"shared\", "
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn config_directory_must_be_absolute() {
+        let home = |name: &str| match name {
+            "HOME" => Some("/home/moon".to_string()),
+            "XDG_CONFIG_HOME" => Some("relative".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            config_directory_from(home).unwrap(),
+            Path::new("/home/moon/.config/natural_satellites_visualiser")
+        );
+        assert!(config_directory_from(|_| None).is_err());
+        assert!(config_directory_from(|_| Some(String::new())).is_err());
+    }
+}
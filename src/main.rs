@@ -1,16 +1,64 @@
 use druid::{
     self,
     widget::{
-        Button, Checkbox, ClipBox, Container, Either, Flex, Label, List, Scroll, SizedBox, TextBox,
+        Button, Checkbox, ClipBox, Container, Either, Flex, Label, LineBreaking, List, Radio,
+        RadioGroup, Scroll, SizedBox, Slider, Split, Tabs, TextBox,
     },
-    AppLauncher, Data, Env, Lens, Point, Widget, WidgetExt, WindowDesc,
+    AppLauncher, Application, Color, Data, Env, Lens, Point, Size, Widget, WidgetExt, WindowDesc,
+};
+use satellite_data::{
+    database::Database,
+    satellites::{MajorBody, Satellite},
 };
-use satellite_data::database::Database;
 use std::env;
+use std::sync::Arc;
 
 pub mod astronomy_canvas;
+pub mod axis;
+pub mod color_map;
+pub mod columns;
+pub mod comparison;
+pub mod completion;
+pub mod fields;
+pub mod filter_builder;
+pub mod filter_editor;
+pub mod functions;
+pub mod histogram;
+pub mod library;
+pub mod pairs;
+pub mod panels;
+pub mod picking;
+pub mod query;
+pub mod selection;
+pub mod spatial_index;
+pub mod summary;
+pub mod system;
+pub mod table;
+#[cfg(test)]
+pub mod test_support;
 
-use astronomy_canvas::{AstronomyCanvas, AstronomyCanvasData, SatelliteWrapper, SatelliteteVec};
+use astronomy_canvas::{
+    AstronomyCanvas, AstronomyCanvasData, SatelliteWrapper, SatelliteteVec, AUTO_FIT,
+};
+use axis::AxisScale;
+use color_map::{ColorMapping, Colormap};
+use columns::{ColumnEditor, ADD_COLUMN, REMOVE_COLUMN};
+use comparison::ComparisonPanel;
+use filter_builder::{
+    high_fraction, low_fraction, Condition, FilterBuilder, Join, Operator, ADD_CONDITION,
+    FROM_TEXT, REMOVE_CONDITION,
+};
+use filter_editor::{CompleteOnTab, FilterAssist};
+use histogram::HistogramStyle;
+use library::{QueryLibrary, SavedQuery, DELETE_QUERY, LOAD_QUERY, RENAME_QUERY, SAVE_QUERY};
+use pairs::{PairFinder, FIND_PAIRS, SELECT_PAIRED};
+use panels::{add_panel, ClosePanel, PanelLens, ViewSettings};
+use query::Query;
+use selection::SelectionTool;
+use spatial_index::SpatialIndex;
+use summary::SummaryPanel;
+use system::SystemView;
+use table::SatelliteTable;
 
 #[macro_use]
 extern crate lazy_static;
@@ -49,21 +97,72 @@ fn main() {
         .title("zggff")
         .window_size((1400.0, 600.0));
 
+    let (queries, library_status) = match library::load_library() {
+        Ok(queries) => (queries, String::new()),
+        Err(error) => (Vec::new(), error),
+    };
+    // computed columns of the last session
+    let (definitions, session_status) = match columns::load_session() {
+        Ok(definitions) => (definitions, String::new()),
+        Err(error) => (Vec::new(), error),
+    };
+    let columns_status = columns::define(&definitions)
+        .err()
+        .unwrap_or(session_status);
     let initaial_state = AstronomyCanvasData {
         all_displayed: DATABASE.data.len(),
         selected: false,
-        center: Point::new(300.0, 300.0),
-        mouse_point: None,
         selected_satellites: SatelliteteVec(Vec::new()),
+        displayed_satellites: SatelliteteVec(
+            DATABASE
+                .data
+                .iter()
+                .map(|satellite| SatelliteWrapper {
+                    satellite: satellite.id,
+                    selected: false,
+                })
+                .collect(),
+        ),
         scale: 1.0,
+        graph_x_scale: 1.0,
+        graph_y_scale: 1.0,
         toggle_angle: false,
         toggle_distance: false,
         toggle_major_semiaxes: false,
         match_string: String::new(),
+        filter_status: String::new(),
+        axis_status: String::new(),
         selected_satellite: None,
         graph_view: false,
         x_value: String::new(),
         y_value: String::new(),
+        color_by_expression: false,
+        color_expression: String::new(),
+        colormap: Colormap::Viridis,
+        x_axis: AxisScale::Linear,
+        y_axis: AxisScale::Linear,
+        toggle_error_bars: false,
+        histogram_view: false,
+        bin_width: String::new(),
+        histogram_style: HistogramStyle::Plain,
+        toggle_kde: false,
+        selection_tool: SelectionTool::Rectangle,
+        system: SystemView::All,
+        panels: Arc::new(Vec::new()),
+        queries: Arc::new(queries),
+        query_name: String::new(),
+        library_status,
+        columns: Arc::new(definitions),
+        column_definition: String::new(),
+        columns_status,
+        summary_terms: "count, mean(albedo), median(density), max(radius)".to_string(),
+        group_by: "mb".to_string(),
+        pair_query: String::new(),
+        pairs: Arc::new(Vec::new()),
+        pair_status: String::new(),
+        filter_conditions: Arc::new(Vec::new()),
+        filter_join: Join::And,
+        builder_status: String::new(),
     };
 
     AppLauncher::with_window(window)
@@ -71,32 +170,344 @@ fn main() {
         .expect("msg")
 }
 
-fn build_root_widget() -> impl Widget<AstronomyCanvasData> {
-    let canvas = AstronomyCanvas {
+fn build_canvas() -> AstronomyCanvas {
+    AstronomyCanvas {
         count_change_timer: None,
         selected_update_timer: None,
         full_database: DATABASE.clone(),
-        selected_satellites: Vec::new(),
+        gesture: None,
         selected_satellite: None,
         move_bul: false,
+        drag_point: None,
+        hovered_satellite: None,
+        hover_point: None,
         database: DATABASE.clone(),
         center: Point::new(300.0, 300.0),
-    };
-    let textfield = TextBox::new()
-        .with_placeholder("mb==\"Uranus\"")
-        .lens(AstronomyCanvasData::match_string);
+        color_mapping: ColorMapping::ByMajorBody,
+        index: SpatialIndex::new(64.0),
+        index_state: None,
+        size: Size::ZERO,
+        tiles: Vec::new(),
+        histogram: None,
+        x_query: Query::new(""),
+        y_query: Query::new(""),
+        filter_status: String::new(),
+    }
+}
+
+// expression errors and evaluation failures, empty when there are none
+fn status_label(
+    text: impl Fn(&AstronomyCanvasData) -> String + 'static,
+) -> impl Widget<AstronomyCanvasData> {
+    let label = Label::new(move |data: &AstronomyCanvasData, _env: &Env| text(data))
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .with_text_color(Color::rgb(1.0, 0.5, 0.4));
+    SizedBox::new(label).width(200.0)
+}
+
+// rows of conditions that write the filter, see `filter_builder`
+fn filter_builder() -> impl Widget<AstronomyCanvasData> {
+    let join = Flex::row()
+        .with_child(Radio::new("all (&&)", Join::And))
+        .with_child(Radio::new("any (||)", Join::Or))
+        .lens(AstronomyCanvasData::filter_join);
+    let buttons = Flex::row()
+        .with_child(
+            Button::new("Add condition")
+                .on_click(|ctx, _data, _env| ctx.submit_command(ADD_CONDITION)),
+        )
+        .with_child(
+            Button::new("From text").on_click(|ctx, _data, _env| ctx.submit_command(FROM_TEXT)),
+        );
+    let rows = List::new(|| {
+        let operator = Button::new(|condition: &Condition, _env: &Env| {
+            condition.operator.symbol().to_string()
+        })
+        .on_click(|_ctx, condition: &mut Condition, _env| {
+            condition.operator = condition.operator.next(condition.numeric)
+        });
+        let range = Flex::column()
+            .with_child(Slider::new().lens(low_fraction()).expand_width())
+            .with_child(Slider::new().lens(high_fraction()).expand_width())
+            .with_child(Label::new(|condition: &Condition, _env: &Env| {
+                format!("{} to {}", condition.low, condition.high)
+            }));
+        let value = Either::new(
+            |condition: &Condition, _env: &Env| condition.operator == Operator::Between,
+            range,
+            TextBox::new()
+                .with_placeholder("value")
+                .lens(Condition::value)
+                .expand_width(),
+        );
+        let unit = Label::new(|condition: &Condition, _env: &Env| condition.unit().to_string());
+        Flex::column()
+            .with_child(
+                Flex::row()
+                    .with_flex_child(
+                        TextBox::new()
+                            .with_placeholder("field")
+                            .lens(Condition::field)
+                            .expand_width(),
+                        1.0,
+                    )
+                    .with_child(operator)
+                    .with_child(Button::new("×").on_click(
+                        |ctx, condition: &mut Condition, _env| {
+                            ctx.submit_command(REMOVE_CONDITION.with(condition.id))
+                        },
+                    )),
+            )
+            .with_child(Flex::row().with_flex_child(value, 1.0).with_child(unit))
+    })
+    .lens(AstronomyCanvasData::filter_conditions);
+    let builder = Flex::column()
+        .with_child(Label::new("Filter builder"))
+        .with_child(join)
+        .with_child(rows)
+        .with_child(buttons)
+        .with_child(status_label(|data| data.builder_status.clone()))
+        .controller(FilterBuilder::new(DATABASE.clone()));
+    SizedBox::new(builder).width(200.0)
+}
+
+// the saved filters and axes, see `library`; the rows send commands to `QueryLibrary`
+fn query_library() -> impl Widget<AstronomyCanvasData> {
+    let name = TextBox::new()
+        .with_placeholder("query name")
+        .lens(AstronomyCanvasData::query_name);
+    let save = Button::new("Save").on_click(|ctx, _data, _env| ctx.submit_command(SAVE_QUERY));
+    let rows = List::new(|| {
+        Flex::row()
+            .with_flex_child(
+                Button::new(|query: &SavedQuery, _env: &Env| query.name.clone()).on_click(
+                    |ctx, query: &mut SavedQuery, _env| {
+                        ctx.submit_command(LOAD_QUERY.with(query.name.clone()))
+                    },
+                ),
+                1.0,
+            )
+            .with_child(
+                Button::new("Rename").on_click(|ctx, query: &mut SavedQuery, _env| {
+                    ctx.submit_command(RENAME_QUERY.with(query.name.clone()))
+                }),
+            )
+            .with_child(
+                Button::new("×").on_click(|ctx, query: &mut SavedQuery, _env| {
+                    ctx.submit_command(DELETE_QUERY.with(query.name.clone()))
+                }),
+            )
+    })
+    .lens(AstronomyCanvasData::queries);
+    let library = Flex::column()
+        .with_child(Flex::row().with_flex_child(name, 1.0).with_child(save))
+        .with_child(rows)
+        .with_child(status_label(|data| data.library_status.clone()))
+        .controller(QueryLibrary);
+    SizedBox::new(library).width(200.0)
+}
+
+// computed columns, see `columns`; the rows send commands to `ColumnEditor`
+fn column_editor() -> impl Widget<AstronomyCanvasData> {
+    let definition = TextBox::new()
+        .with_placeholder("hill = a * (gm / (3 * parent_gm)) ^ (1.0 / 3)")
+        .lens(AstronomyCanvasData::column_definition);
+    let add = Button::new("Add").on_click(|ctx, _data, _env| ctx.submit_command(ADD_COLUMN));
+    let rows = List::new(|| {
+        Flex::row()
+            .with_flex_child(
+                Label::new(|definition: &String, _env: &Env| definition.clone())
+                    .with_line_break_mode(LineBreaking::WordWrap),
+                1.0,
+            )
+            .with_child(
+                Button::new("×").on_click(|ctx, definition: &mut String, _env| {
+                    let name = definition.split('=').next().unwrap_or("").trim();
+                    ctx.submit_command(REMOVE_COLUMN.with(name.to_string()))
+                }),
+            )
+    })
+    .lens(AstronomyCanvasData::columns);
+    let editor = Flex::column()
+        .with_child(Label::new("Computed columns"))
+        .with_child(Flex::row().with_flex_child(definition, 1.0).with_child(add))
+        .with_child(rows)
+        .with_child(status_label(|data| data.columns_status.clone()))
+        .controller(ColumnEditor);
+    SizedBox::new(editor).width(200.0)
+}
+
+// the pair query, see `pairs`; the pairs found are drawn on the graph
+fn pair_finder() -> impl Widget<AstronomyCanvasData> {
+    let query = TextBox::new()
+        .with_placeholder("mb == other.mb && abs(a / other.a - 1) < 0.05")
+        .lens(AstronomyCanvasData::pair_query);
+    let buttons = Flex::row()
+        .with_child(
+            Button::new("Find pairs").on_click(|ctx, _data, _env| ctx.submit_command(FIND_PAIRS)),
+        )
+        .with_child(
+            Button::new("Select paired")
+                .on_click(|ctx, _data, _env| ctx.submit_command(SELECT_PAIRED)),
+        );
+    let finder = Flex::column()
+        .with_child(Label::new("Pairs"))
+        .with_child(SizedBox::new(query).width(200.0))
+        .with_child(buttons)
+        .with_child(status_label(|data| data.pair_status.clone()))
+        .controller(PairFinder::new(DATABASE.clone()));
+    SizedBox::new(finder).width(200.0)
+}
+
+// aggregates of the filtered satellites, see `summary`
+fn summary_tab() -> impl Widget<AstronomyCanvasData> {
+    let terms = TextBox::new()
+        .with_placeholder("count, mean(albedo), max(radius)")
+        .lens(AstronomyCanvasData::summary_terms);
+    let group_by = TextBox::new()
+        .with_placeholder("mb")
+        .lens(AstronomyCanvasData::group_by);
+    let controls = Flex::row()
+        .with_child(Label::new("aggregates"))
+        .with_flex_child(terms.expand_width(), 2.0)
+        .with_child(Label::new("group by"))
+        .with_flex_child(group_by.expand_width(), 1.0);
+    Flex::column()
+        .with_child(controls)
+        .with_flex_child(SummaryPanel::new(DATABASE.clone()), 1.0)
+}
+
+// controls of the settings every view has for itself, see `panels::ViewSettings`
+fn view_controls() -> impl Widget<AstronomyCanvasData> {
     let x_value = TextBox::new()
         .with_placeholder("x = a")
         .lens(AstronomyCanvasData::x_value);
     let y_value = TextBox::new()
         .with_placeholder("y=a")
         .lens(AstronomyCanvasData::y_value);
+    let x_axis = Flex::row()
+        .with_child(Radio::new("lin", AxisScale::Linear))
+        .with_child(Radio::new("log", AxisScale::Log))
+        .with_child(Radio::new("symlog", AxisScale::Symlog))
+        .lens(AstronomyCanvasData::x_axis);
+    let y_axis = Flex::row()
+        .with_child(Radio::new("lin", AxisScale::Linear))
+        .with_child(Radio::new("log", AxisScale::Log))
+        .with_child(Radio::new("symlog", AxisScale::Symlog))
+        .lens(AstronomyCanvasData::y_axis);
+    let error_bars_checkbox =
+        Checkbox::new("Toggle error bars").lens(AstronomyCanvasData::toggle_error_bars);
     let distance_checkbox =
         Checkbox::new("Toggle Distances").lens(AstronomyCanvasData::toggle_distance);
     let angle_checkbox = Checkbox::new("Toggle Angles").lens(AstronomyCanvasData::toggle_angle);
     let semiaxes_checkbox =
         Checkbox::new("Toggle Semiaxes").lens(AstronomyCanvasData::toggle_major_semiaxes);
+    let histogram_checkbox =
+        Checkbox::new("Histogram of x").lens(AstronomyCanvasData::histogram_view);
+    let bin_width = TextBox::new()
+        .with_placeholder("bin width (auto)")
+        .lens(AstronomyCanvasData::bin_width);
+    let histogram_style = RadioGroup::new(vec![
+        ("Single colour", HistogramStyle::Plain),
+        ("Stacked by parent", HistogramStyle::Stacked),
+        ("Coloured by parent", HistogramStyle::Overlaid),
+    ])
+    .lens(AstronomyCanvasData::histogram_style);
+    let kde_checkbox = Checkbox::new("Density curve").lens(AstronomyCanvasData::toggle_kde);
     let graph_checkbox = Checkbox::new("Toggle graph mode").lens(AstronomyCanvasData::graph_view);
+    let auto_fit_button =
+        Button::new("Auto-fit").on_click(|ctx, _data, _env| ctx.submit_command(AUTO_FIT));
+    let either = Either::new(
+        |data: &AstronomyCanvasData, _env: &Env| data.graph_view,
+        Flex::column()
+            .with_child(x_value)
+            .with_child(x_axis)
+            .with_child(histogram_checkbox)
+            .with_child(Either::new(
+                |data: &AstronomyCanvasData, _env: &Env| data.histogram_view,
+                Flex::column()
+                    .with_child(bin_width)
+                    .with_child(histogram_style)
+                    .with_child(kde_checkbox),
+                Flex::column()
+                    .with_child(y_value)
+                    .with_child(y_axis)
+                    .with_child(error_bars_checkbox),
+            ))
+            .with_child(status_label(|data| data.axis_status.clone())),
+        Flex::column()
+            .with_child(distance_checkbox)
+            .with_child(semiaxes_checkbox)
+            .with_child(angle_checkbox),
+    );
+    Flex::column()
+        .with_child(graph_checkbox)
+        .with_child(auto_fit_button)
+        .with_child(either)
+}
+
+fn build_panel(id: usize) -> impl Widget<AstronomyCanvasData> {
+    Flex::row()
+        .with_flex_child(SizedBox::new(view_controls()).width(210.0), 0.0)
+        .with_flex_child(ClipBox::new(build_canvas()), 1.0)
+        .lens(PanelLens(id))
+        .controller(ClosePanel(id))
+}
+
+fn build_root_widget() -> impl Widget<AstronomyCanvasData> {
+    let canvas = build_canvas();
+    let textfield = TextBox::new()
+        .with_placeholder("mb==\"Uranus\"")
+        .lens(AstronomyCanvasData::match_string)
+        .controller(CompleteOnTab::new(&DATABASE));
+    let color_checkbox =
+        Checkbox::new("Color by expression").lens(AstronomyCanvasData::color_by_expression);
+    let color_expression = TextBox::new()
+        .with_placeholder("albedo")
+        .lens(AstronomyCanvasData::color_expression);
+    let colormap = RadioGroup::new(vec![
+        ("Viridis", Colormap::Viridis),
+        ("Magma", Colormap::Magma),
+        ("Inferno", Colormap::Inferno),
+        ("Cividis", Colormap::Cividis),
+    ])
+    .lens(AstronomyCanvasData::colormap);
+    let color_controls = Flex::column()
+        .with_child(color_checkbox)
+        .with_child(Either::new(
+            |data: &AstronomyCanvasData, _env: &Env| data.color_by_expression,
+            Flex::column()
+                .with_child(color_expression)
+                .with_child(colormap),
+            SizedBox::empty(),
+        ));
+    let selection_tool = Flex::row()
+        .with_child(Radio::new("box", SelectionTool::Rectangle))
+        .with_child(Radio::new("lasso", SelectionTool::Lasso))
+        .with_child(Radio::new("polygon", SelectionTool::Polygon))
+        .lens(AstronomyCanvasData::selection_tool);
+    let mut system_selector = Flex::column().with_child(
+        Flex::row()
+            .with_child(Radio::new("all systems", SystemView::All))
+            .with_child(Radio::new("tiled", SystemView::SmallMultiples)),
+    );
+    for bodies in MajorBody::ALL.chunks(3) {
+        let mut row = Flex::row();
+        for body in bodies {
+            row.add_child(Radio::new(body.to_string(), SystemView::Single(*body)));
+        }
+        system_selector.add_child(row);
+    }
+    let system_selector = system_selector.lens(AstronomyCanvasData::system);
+    let open_view_button =
+        Button::new("Open linked view").on_click(|ctx, data: &mut AstronomyCanvasData, _env| {
+            let id = add_panel(data, ViewSettings::scatter("a", "i"));
+            ctx.new_window(
+                WindowDesc::new(move || build_panel(id))
+                    .title(format!("zggff view {}", id + 1))
+                    .window_size((900.0, 600.0)),
+            );
+        });
     let label = Label::new(|data: &AstronomyCanvasData, _env: &Env| data.all_displayed.to_string());
     let label1 = Label::new(|data: &AstronomyCanvasData, _env: &Env| {
         data.selected_satellites.0.len().to_string()
@@ -121,23 +532,26 @@ fn build_root_widget() -> impl Widget<AstronomyCanvasData> {
         .height(250.0)
         .width(200.0);
 
-    let either = Either::new(
-        |data: &AstronomyCanvasData, _env: &Env| data.graph_view,
-        Flex::column().with_child(x_value).with_child(y_value),
-        Flex::column()
-            .with_child(distance_checkbox)
-            .with_child(semiaxes_checkbox)
-            .with_child(angle_checkbox),
-    );
-
     let left_controls = SizedBox::new(Container::new(
-        Flex::column()
-            .with_child(SizedBox::new(textfield).width(200.0).height(200.0))
-            .with_child(graph_checkbox)
-            .with_child(either)
-            .with_child(label)
-            .with_child(label1)
-            .with_child(scroll),
+        Scroll::new(
+            Flex::column()
+                .with_child(SizedBox::new(textfield).width(200.0).height(120.0))
+                .with_child(status_label(|data| data.filter_status.clone()))
+                .with_child(SizedBox::new(FilterAssist::new(&DATABASE)).width(200.0))
+                .with_child(filter_builder())
+                .with_child(query_library())
+                .with_child(column_editor())
+                .with_child(pair_finder())
+                .with_child(system_selector)
+                .with_child(selection_tool)
+                .with_child(view_controls())
+                .with_child(open_view_button)
+                .with_child(color_controls)
+                .with_child(label)
+                .with_child(label1)
+                .with_child(scroll),
+        )
+        .vertical(),
     ))
     .width(210.0);
 
@@ -156,103 +570,73 @@ fn build_root_widget() -> impl Widget<AstronomyCanvasData> {
         )
         .with_text_size(20.0);
 
-    let orbital_data =
+    let details =
         Label::new(
-            |data: &AstronomyCanvasData, _env: &Env| match data.selected_satellite {
-                Some(value) => {
-                    let sat = DATABASE.get_satellite_by_id(value.satellite);
-                    match sat {
-                        Some(sat) => {
-                            let mut satellite_orbital_data = String::new();
-                            satellite_orbital_data.push_str("MajorBody:\t");
-                            satellite_orbital_data.push_str(&sat.major_body.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data.push_str("a:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.orbital_params.major_semiaxis.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data.push_str("e:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.orbital_params.eccentricity.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data.push_str("i:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.orbital_params.inclination.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data.push_str("node:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.orbital_params.ascending_node.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data
-                        }
-                        None => "".to_string(),
-                    }
-                }
+            |data: &AstronomyCanvasData, _env: &Env| match selected_satellite(data) {
+                Some(sat) => fields::record_text(&sat),
                 None => "".to_string(),
             },
         );
-
-    let physical_data =
-        Label::new(
-            |data: &AstronomyCanvasData, _env: &Env| match data.selected_satellite {
-                Some(value) => {
-                    let sat = DATABASE.get_satellite_by_id(value.satellite);
-                    match sat {
-                        Some(sat) => {
-                            let mut satellite_orbital_data = String::new();
-                            satellite_orbital_data.push_str("Gm:\t");
-                            satellite_orbital_data.push_str(&sat.physical_params.gm.to_string());
-                            satellite_orbital_data.push_str(" km3/sec2\n");
-
-                            satellite_orbital_data.push_str("radius:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.physical_params.radius.to_string());
-                            satellite_orbital_data.push_str(" km\n");
-
-                            satellite_orbital_data.push_str("density:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.physical_params.density.to_string());
-                            satellite_orbital_data.push_str(" g/cm3\n");
-
-                            satellite_orbital_data.push_str("magnitude:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.physical_params.magnitude.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data.push_str("albedo:\t");
-                            satellite_orbital_data
-                                .push_str(&sat.physical_params.albedo.to_string());
-                            satellite_orbital_data.push('\n');
-
-                            satellite_orbital_data
-                        }
-                        None => "".to_string(),
-                    }
+    let copy_buttons = Flex::row()
+        .with_child(Button::new("Copy text").on_click(
+            |_ctx, data: &mut AstronomyCanvasData, _env| {
+                if let Some(sat) = selected_satellite(data) {
+                    Application::global()
+                        .clipboard()
+                        .put_string(fields::record_text(&sat));
                 }
-                None => "".to_string(),
             },
-        );
+        ))
+        .with_child(Button::new("Copy JSON").on_click(
+            |_ctx, data: &mut AstronomyCanvasData, _env| {
+                if let Some(sat) = selected_satellite(data) {
+                    Application::global()
+                        .clipboard()
+                        .put_string(fields::record_json(&sat));
+                }
+            },
+        ));
 
     let right_controls = SizedBox::new(
         Scroll::new(
             Flex::column()
                 .with_child(label)
-                // .with_child()
-                .with_child(orbital_data)
-                .with_child(physical_data),
+                .with_child(copy_buttons)
+                .with_child(details),
         )
         .horizontal(),
     )
-    .width(260.0);
+    .width(320.0);
 
-    let canvas = ClipBox::new(canvas);
+    let tabs = Tabs::new()
+        .with_tab("Table", SatelliteTable::new(DATABASE.clone()))
+        .with_tab("Compare", ComparisonPanel::new(DATABASE.clone()))
+        .with_tab("Summary", summary_tab())
+        .with_tab(
+            "Variables",
+            Scroll::new(
+                // computed columns are listed as they are defined
+                Label::new(|_data: &AstronomyCanvasData, _env: &Env| {
+                    format!(
+                        "{}\n{}",
+                        fields::variables_text(),
+                        functions::functions_text()
+                    )
+                })
+                .with_text_size(12.0),
+            )
+            .vertical(),
+        );
+    let canvas = Split::rows(ClipBox::new(canvas), tabs)
+        .split_point(0.7)
+        .draggable(true);
     Flex::row()
         .with_flex_child(left_controls, 0.0)
         .with_flex_child(canvas, 1.0)
         .with_flex_child(right_controls, 0.0)
 }
+
+fn selected_satellite(data: &AstronomyCanvasData) -> Option<Satellite> {
+    data.selected_satellite
+        .and_then(|value| DATABASE.get_satellite_by_id(value.satellite))
+}
@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, Selector, Widget};
+use evalexpr::{
+    build_operator_tree, Context, ContextWithMutableVariables, HashMapContext, Node, Value,
+};
+use satellite_data::{database::Database, satellites::Satellite};
+
+use crate::astronomy_canvas::{select, AstronomyCanvasData};
+use crate::columns;
+use crate::fields;
+use crate::functions;
+use crate::query::describe;
+use crate::selection::SelectionMode;
+
+pub const FIND_PAIRS: Selector = Selector::new("pairs.find");
+pub const SELECT_PAIRED: Selector = Selector::new("pairs.select");
+
+// variables of the second satellite of a pair carry this prefix
+pub const OTHER: &str = "other.";
+// most pairs kept, so the canvas stays readable
+const MAX_PAIRS: usize = 2000;
+// most pairs tried, so a large selection does not stall the interface
+const MAX_COMPARISONS: usize = 1_000_000;
+
+// a condition on two satellites, such as `mb == other.mb && abs(a / other.a - 1) < 0.05`,
+// where plain names refer to the first satellite and `other.` names to the second
+#[derive(Clone, Debug)]
+pub struct PairQuery {
+    source: String,
+    tree: Option<Node>,
+    error: Option<String>,
+}
+
+// pairs of satellite ids the condition holds for, each pair listed once
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PairResult {
+    pub pairs: Vec<(usize, usize)>,
+    pub failures: usize,
+    pub first_failure: Option<String>,
+    pub truncated: bool,
+    pub compared: usize,
+}
+
+impl PairQuery {
+    pub fn new(source: &str) -> PairQuery {
+        let (tree, error) = if source.trim().is_empty() {
+            (None, None)
+        } else {
+            match build_operator_tree(source) {
+                Ok(tree) => (Some(tree), None),
+                Err(error) => (None, Some(describe(source, &error))),
+            }
+        };
+        PairQuery {
+            source: source.to_string(),
+            tree,
+            error,
+        }
+    }
+
+    // every ordered pair of distinct satellites is tried, a pair that matches both
+    // ways is kept once in the order first found; a pair for which a value used is
+    // unknown does not match
+    pub fn find(&self, satellites: &[Satellite]) -> Result<PairResult, String> {
+        self.find_within(satellites, MAX_COMPARISONS)
+    }
+
+    fn find_within(
+        &self,
+        satellites: &[Satellite],
+        max_comparisons: usize,
+    ) -> Result<PairResult, String> {
+        let tree = match (&self.tree, &self.error) {
+            (_, Some(error)) => return Err(format!("bad expression: {}", error)),
+            (Some(tree), None) => tree,
+            (None, None) => return Ok(PairResult::default()),
+        };
+        let identifiers: Vec<&str> = tree.iter_variable_identifiers().collect();
+        let mut names: Vec<&str> = identifiers
+            .iter()
+            .map(|name| name.strip_prefix(OTHER).unwrap_or(name))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        let used = |name: &str| identifiers.contains(&name);
+
+        // only the variables the expression uses are looked up, once per satellite,
+        // along with whether all of them are known for it as first and as other
+        let mut values: Vec<Vec<Value>> = Vec::with_capacity(satellites.len());
+        let mut known: Vec<(bool, bool)> = Vec::with_capacity(satellites.len());
+        for satellite in satellites {
+            let context = fields::context(satellite);
+            let mut row = Vec::with_capacity(names.len());
+            let (mut as_first, mut as_other) = (true, true);
+            for name in &names {
+                match context.get_value(name) {
+                    Some(value) => row.push(value.clone()),
+                    None => return Err(format!("unknown variable {}", name)),
+                }
+                if columns::is_unknown(&context, satellite, name) {
+                    as_first &= !used(name);
+                    as_other &= !used(&format!("{}{}", OTHER, name));
+                }
+            }
+            values.push(row);
+            known.push((as_first, as_other));
+        }
+
+        let mut context = HashMapContext::new();
+        functions::register(&mut context);
+        let mut result = PairResult::default();
+        let mut found = HashSet::new();
+        for (first, first_values) in values.iter().enumerate() {
+            if !known[first].0 {
+                continue;
+            }
+            for (second, second_values) in values.iter().enumerate() {
+                if first == second || !known[second].1 {
+                    continue;
+                }
+                if result.compared == max_comparisons {
+                    result.truncated = true;
+                    return Ok(result);
+                }
+                result.compared += 1;
+                for (index, name) in names.iter().enumerate() {
+                    context
+                        .set_value(name.to_string(), first_values[index].clone())
+                        .unwrap();
+                    context
+                        .set_value(format!("{}{}", OTHER, name), second_values[index].clone())
+                        .unwrap();
+                }
+                match tree.eval_boolean_with_context(&context) {
+                    Ok(true) => {
+                        let (a, b) = (satellites[first].id, satellites[second].id);
+                        if !found.insert((a.min(b), a.max(b))) {
+                            continue;
+                        }
+                        if result.pairs.len() == MAX_PAIRS {
+                            result.truncated = true;
+                            return Ok(result);
+                        }
+                        result.pairs.push((a, b));
+                    }
+                    Ok(false) => {}
+                    Err(error) => {
+                        result.failures += 1;
+                        if result.first_failure.is_none() {
+                            result.first_failure = Some(describe(&self.source, &error));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl PairResult {
+    pub fn status(&self, satellites: usize) -> String {
+        let mut status = format!("{} pairs among {} satellites", self.pairs.len(), satellites);
+        if self.truncated && self.pairs.len() == MAX_PAIRS {
+            status = format!("first {} pairs, stopped", self.pairs.len());
+        } else if self.truncated {
+            status = format!(
+                "{} pairs in the first {} comparisons, stopped",
+                self.pairs.len(),
+                self.compared
+            );
+        }
+        if let Some(failure) = &self.first_failure {
+            status.push_str(&format!("\n{} pairs failed: {}", self.failures, failure));
+        }
+        status
+    }
+}
+
+// runs the pair query over the filtered satellites when asked to, see `PairQuery`
+pub struct PairFinder {
+    database: Database,
+}
+
+impl PairFinder {
+    pub fn new(database: Database) -> PairFinder {
+        PairFinder { database }
+    }
+}
+
+impl<W: Widget<AstronomyCanvasData>> Controller<AstronomyCanvasData, W> for PairFinder {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(command) if command.is(FIND_PAIRS) => {
+                let satellites: Vec<Satellite> = data
+                    .displayed_satellites
+                    .0
+                    .iter()
+                    .filter_map(|wrapper| self.database.get_satellite_by_id(wrapper.satellite))
+                    .collect();
+                match PairQuery::new(&data.pair_query).find(&satellites) {
+                    Ok(result) => {
+                        data.pair_status = result.status(satellites.len());
+                        data.pairs = Arc::new(result.pairs);
+                    }
+                    Err(error) => {
+                        data.pair_status = error;
+                        data.pairs = Arc::new(Vec::new());
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.is(SELECT_PAIRED) => {
+                let mut ids: Vec<usize> = data
+                    .pairs
+                    .iter()
+                    .flat_map(|(first, second)| [*first, *second])
+                    .collect();
+                ids.sort_unstable();
+                ids.dedup();
+                select(data, &ids, SelectionMode::Replace);
+                ctx.set_handled();
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{satellite, SatelliteBuilder, UNKNOWN};
+    use satellite_data::satellites::MajorBody;
+
+    fn moon(id: usize, name: &str, major_body: MajorBody, major_semiaxis: f64) -> Satellite {
+        satellite(id, major_body, major_semiaxis).named(name)
+    }
+
+    #[test]
+    fn pairs_are_found_once() {
+        let satellites = vec![
+            moon(0, "Tethys", MajorBody::Saturn, 294619.0),
+            moon(1, "Telesto", MajorBody::Saturn, 294619.0),
+            moon(2, "Calypso", MajorBody::Saturn, 294619.0),
+            moon(3, "Titan", MajorBody::Saturn, 1221870.0),
+            moon(4, "Hyperion", MajorBody::Saturn, 1481010.0),
+            moon(5, "Io", MajorBody::Jupiter, 294619.0),
+        ];
+        let coorbital = PairQuery::new("mb == other.mb && abs(a / other.a - 1) < 0.05");
+        let result = coorbital.find(&satellites).unwrap();
+        assert_eq!(result.pairs, vec![(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(result.status(6), "3 pairs among 6 satellites");
+
+        // Hyperion is in a 4:3 resonance with Titan
+        let resonance = PairQuery::new(
+            "other.name == \"Titan\" && abs(period / other.period - 4.0 / 3) < 0.02",
+        );
+        assert_eq!(resonance.find(&satellites).unwrap().pairs, vec![(4, 3)]);
+
+        assert!(PairQuery::new("a == other.foo").find(&satellites).is_err());
+        assert!(PairQuery::new("a > (").find(&satellites).is_err());
+        let failing = PairQuery::new("name > other.a").find(&satellites).unwrap();
+        assert_eq!(failing.failures, 30);
+
+        let stopped = coorbital.find_within(&satellites, 6).unwrap();
+        assert!(stopped.truncated);
+        assert_eq!(stopped.pairs, vec![(0, 1), (0, 2)]);
+        assert_eq!(
+            stopped.status(6),
+            "2 pairs in the first 6 comparisons, stopped"
+        );
+    }
+
+    #[test]
+    fn unknown_values_pair_with_nothing() {
+        let satellites = vec![
+            moon(0, "A", MajorBody::Saturn, 1000.0).gm(2.0),
+            moon(1, "B", MajorBody::Saturn, 1000.0).gm(2.5),
+            moon(2, "C", MajorBody::Saturn, 1000.0).gm(UNKNOWN),
+            moon(3, "D", MajorBody::Saturn, 1000.0).gm(UNKNOWN),
+        ];
+        let close = PairQuery::new("abs(gm - other.gm) < 1").find(&satellites);
+        assert_eq!(close.unwrap().pairs, vec![(0, 1)]);
+        // an unknown value that is not used does not matter
+        let named = PairQuery::new("gm > 1 && other.name == \"C\"").find(&satellites);
+        assert_eq!(named.unwrap().pairs, vec![(0, 2), (1, 2)]);
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use druid::widget::Controller;
+use druid::{Data, Env, Event, EventCtx, Lens, Widget};
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+use crate::axis::AxisScale;
+use crate::histogram::HistogramStyle;
+
+// the part of `AstronomyCanvasData` that belongs to a single view; the database,
+// filter and selection are shared by every view
+#[derive(Clone, Data, PartialEq, Debug)]
+pub struct ViewSettings {
+    pub graph_view: bool,
+    pub scale: f64,
+    pub graph_x_scale: f64,
+    pub graph_y_scale: f64,
+    pub x_value: String,
+    pub y_value: String,
+    pub x_axis: AxisScale,
+    pub y_axis: AxisScale,
+    pub toggle_error_bars: bool,
+    pub histogram_view: bool,
+    pub bin_width: String,
+    pub histogram_style: HistogramStyle,
+    pub toggle_kde: bool,
+    pub axis_status: String,
+    pub toggle_distance: bool,
+    pub toggle_angle: bool,
+    pub toggle_major_semiaxes: bool,
+}
+
+impl ViewSettings {
+    pub fn scatter(x_value: &str, y_value: &str) -> ViewSettings {
+        ViewSettings {
+            graph_view: true,
+            scale: 1.0,
+            graph_x_scale: 1.0,
+            graph_y_scale: 1.0,
+            x_value: x_value.to_string(),
+            y_value: y_value.to_string(),
+            x_axis: AxisScale::Linear,
+            y_axis: AxisScale::Linear,
+            toggle_error_bars: false,
+            histogram_view: false,
+            bin_width: String::new(),
+            histogram_style: HistogramStyle::Plain,
+            toggle_kde: false,
+            axis_status: String::new(),
+            toggle_distance: false,
+            toggle_angle: false,
+            toggle_major_semiaxes: false,
+        }
+    }
+
+    pub fn from_data(data: &AstronomyCanvasData) -> ViewSettings {
+        ViewSettings {
+            graph_view: data.graph_view,
+            scale: data.scale,
+            graph_x_scale: data.graph_x_scale,
+            graph_y_scale: data.graph_y_scale,
+            x_value: data.x_value.clone(),
+            y_value: data.y_value.clone(),
+            x_axis: data.x_axis,
+            y_axis: data.y_axis,
+            toggle_error_bars: data.toggle_error_bars,
+            histogram_view: data.histogram_view,
+            bin_width: data.bin_width.clone(),
+            histogram_style: data.histogram_style,
+            toggle_kde: data.toggle_kde,
+            axis_status: data.axis_status.clone(),
+            toggle_distance: data.toggle_distance,
+            toggle_angle: data.toggle_angle,
+            toggle_major_semiaxes: data.toggle_major_semiaxes,
+        }
+    }
+
+    pub fn apply(&self, data: &mut AstronomyCanvasData) {
+        data.graph_view = self.graph_view;
+        data.scale = self.scale;
+        data.graph_x_scale = self.graph_x_scale;
+        data.graph_y_scale = self.graph_y_scale;
+        data.x_value = self.x_value.clone();
+        data.y_value = self.y_value.clone();
+        data.x_axis = self.x_axis;
+        data.y_axis = self.y_axis;
+        data.toggle_error_bars = self.toggle_error_bars;
+        data.histogram_view = self.histogram_view;
+        data.bin_width = self.bin_width.clone();
+        data.histogram_style = self.histogram_style;
+        data.toggle_kde = self.toggle_kde;
+        data.axis_status = self.axis_status.clone();
+        data.toggle_distance = self.toggle_distance;
+        data.toggle_angle = self.toggle_angle;
+        data.toggle_major_semiaxes = self.toggle_major_semiaxes;
+    }
+}
+
+// the settings of one linked view, the id stays the same while other views close
+#[derive(Clone, Data, PartialEq, Debug)]
+pub struct Panel {
+    pub id: usize,
+    pub settings: ViewSettings,
+}
+
+pub fn add_panel(data: &mut AstronomyCanvasData, settings: ViewSettings) -> usize {
+    let id = data
+        .panels
+        .iter()
+        .map(|panel| panel.id + 1)
+        .max()
+        .unwrap_or(0);
+    let mut panels = (*data.panels).clone();
+    panels.push(Panel { id, settings });
+    data.panels = Arc::new(panels);
+    id
+}
+
+pub fn remove_panel(data: &mut AstronomyCanvasData, id: usize) {
+    if data.panels.iter().any(|panel| panel.id == id) {
+        let mut panels = (*data.panels).clone();
+        panels.retain(|panel| panel.id != id);
+        data.panels = Arc::new(panels);
+    }
+}
+
+// presents the shared data with the settings of the panel with the wrapped id in
+// place of the main view's, so an `AstronomyCanvas` and its controls can be reused unchanged
+pub struct PanelLens(pub usize);
+
+impl PanelLens {
+    fn panel<'a>(&self, data: &'a AstronomyCanvasData) -> Option<&'a Panel> {
+        data.panels.iter().find(|panel| panel.id == self.0)
+    }
+
+    fn view(&self, data: &AstronomyCanvasData) -> AstronomyCanvasData {
+        let mut view = data.clone();
+        if let Some(panel) = self.panel(data) {
+            panel.settings.apply(&mut view);
+        }
+        view
+    }
+}
+
+impl Lens<AstronomyCanvasData, AstronomyCanvasData> for PanelLens {
+    fn with<V, F: FnOnce(&AstronomyCanvasData) -> V>(&self, data: &AstronomyCanvasData, f: F) -> V {
+        f(&self.view(data))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut AstronomyCanvasData) -> V>(
+        &self,
+        data: &mut AstronomyCanvasData,
+        f: F,
+    ) -> V {
+        let mut view = self.view(data);
+        let result = f(&mut view);
+        let settings = ViewSettings::from_data(&view);
+        let main_view = ViewSettings::from_data(data);
+        *data = view;
+        main_view.apply(data);
+        if self
+            .panel(data)
+            .map_or(false, |panel| panel.settings != settings)
+        {
+            let mut panels = (*data.panels).clone();
+            for panel in panels.iter_mut().filter(|panel| panel.id == self.0) {
+                panel.settings = settings.clone();
+            }
+            data.panels = Arc::new(panels);
+        }
+        result
+    }
+}
+
+// forgets the settings of a linked view once its window is closed
+pub struct ClosePanel(pub usize);
+
+impl<W: Widget<AstronomyCanvasData>> Controller<AstronomyCanvasData, W> for ClosePanel {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        env: &Env,
+    ) {
+        if let Event::WindowDisconnected = event {
+            remove_panel(data, self.0);
+        }
+        child.event(ctx, event, data, env)
+    }
+}
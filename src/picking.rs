@@ -0,0 +1,166 @@
+use druid::kurbo::{Ellipse, Point, Rect, Vec2};
+use satellite_data::satellites::Satellite;
+
+// tolerances in pixels for a click or hover to count as a hit
+pub const ORBIT_TOLERANCE: f64 = 5.0;
+pub const POINT_TOLERANCE: f64 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitShape {
+    pub center: Point,
+    pub radii: Vec2,
+    pub rotation: f64,
+}
+
+impl OrbitShape {
+    // screen space orbit of a satellite around `origin`, `scale` is in km per pixel
+    pub fn new(satellite: &Satellite, origin: Point, scale: f64) -> OrbitShape {
+        let a = satellite.orbital_params.major_semiaxis;
+        let e = satellite.orbital_params.eccentricity;
+        let b = (a.powi(2) * (1.0 - e.powi(2))).sqrt();
+        let rotation = satellite.orbital_params.inclination.to_radians();
+        let center = Point::new(
+            origin.x + (a * e) * rotation.cos() / scale,
+            origin.y + (a * e) * rotation.sin() / scale,
+        );
+        OrbitShape {
+            center,
+            radii: Vec2::new(a / scale, b / scale),
+            rotation,
+        }
+    }
+
+    pub fn to_ellipse(self) -> Ellipse {
+        Ellipse::new(self.center, self.radii, self.rotation)
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let (sin, cos) = self.rotation.sin_cos();
+        let half_width = (self.radii.x * cos).hypot(self.radii.y * sin);
+        let half_height = (self.radii.x * sin).hypot(self.radii.y * cos);
+        Rect::from_center_size(self.center, (2.0 * half_width, 2.0 * half_height))
+    }
+
+    pub fn distance(&self, point: Point) -> f64 {
+        let offset = point - self.center;
+        let (sin, cos) = self.rotation.sin_cos();
+        let local = Point::new(
+            offset.x * cos + offset.y * sin,
+            -offset.x * sin + offset.y * cos,
+        );
+        let closest = closest_point_on_ellipse(self.radii.x, self.radii.y, local);
+        local.distance(closest)
+    }
+}
+
+// closest point on the axis aligned ellipse x²/a² + y²/b² = 1, found by a few
+// newton-like steps on the evolute which converge for every point of the plane
+pub fn closest_point_on_ellipse(a: f64, b: f64, point: Point) -> Point {
+    if a <= 0.0 || b <= 0.0 {
+        let x = point.x.max(-a.abs()).min(a.abs());
+        let y = point.y.max(-b.abs()).min(b.abs());
+        return Point::new(x, y);
+    }
+    let px = point.x.abs();
+    let py = point.y.abs();
+    let mut tx = std::f64::consts::FRAC_1_SQRT_2;
+    let mut ty = std::f64::consts::FRAC_1_SQRT_2;
+    for _ in 0..4 {
+        let x = a * tx;
+        let y = b * ty;
+        let ex = (a * a - b * b) * tx.powi(3) / a;
+        let ey = (b * b - a * a) * ty.powi(3) / b;
+        let r = (x - ex).hypot(y - ey);
+        let q = (px - ex).hypot(py - ey);
+        if q == 0.0 {
+            break;
+        }
+        tx = (((px - ex) * r / q + ex) / a).max(0.0).min(1.0);
+        ty = (((py - ey) * r / q + ey) / b).max(0.0).min(1.0);
+        let t = tx.hypot(ty);
+        tx /= t;
+        ty /= t;
+    }
+    Point::new((a * tx).copysign(point.x), (b * ty).copysign(point.y))
+}
+
+// returns the key of the candidate with the smallest distance not exceeding `tolerance`
+pub fn closest<K>(candidates: impl Iterator<Item = (K, f64)>, tolerance: f64) -> Option<K> {
+    candidates
+        .filter(|(_, distance)| distance.is_finite() && *distance <= tolerance)
+        .fold(None, |best: Option<(K, f64)>, (key, distance)| match best {
+            Some((_, best_distance)) if best_distance <= distance => best,
+            _ => Some((key, distance)),
+        })
+        .map(|(key, _)| key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(center: (f64, f64), radii: (f64, f64), rotation: f64) -> OrbitShape {
+        OrbitShape {
+            center: center.into(),
+            radii: radii.into(),
+            rotation,
+        }
+    }
+
+    #[test]
+    fn distance_to_circle() {
+        let circle = shape((10.0, 10.0), (5.0, 5.0), 0.0);
+        assert!((circle.distance(Point::new(10.0, 10.0)) - 5.0).abs() < 1e-6);
+        assert!((circle.distance(Point::new(20.0, 10.0)) - 5.0).abs() < 1e-6);
+        assert!((circle.distance(Point::new(13.0, 14.0))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn distance_to_ellipse_vertices() {
+        let ellipse = shape((0.0, 0.0), (100.0, 20.0), 0.0);
+        assert!((ellipse.distance(Point::new(110.0, 0.0)) - 10.0).abs() < 1e-6);
+        assert!((ellipse.distance(Point::new(0.0, 25.0)) - 5.0).abs() < 1e-6);
+        assert!((ellipse.distance(Point::new(0.0, 0.0)) - 20.0).abs() < 1e-6);
+        assert!((ellipse.distance(Point::new(-100.0, 0.0))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn distance_to_rotated_ellipse() {
+        let ellipse = shape((5.0, 5.0), (100.0, 20.0), std::f64::consts::FRAC_PI_2);
+        assert!((ellipse.distance(Point::new(5.0, 110.0)) - 5.0).abs() < 1e-6);
+        assert!((ellipse.distance(Point::new(30.0, 5.0)) - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bounds_of_rotated_ellipse() {
+        let ellipse = shape((0.0, 0.0), (100.0, 20.0), std::f64::consts::FRAC_PI_2);
+        let bounds = ellipse.bounds();
+        assert!((bounds.width() - 40.0).abs() < 1e-6);
+        assert!((bounds.height() - 200.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn closest_point_lies_on_ellipse() {
+        let (a, b) = (50.0, 10.0);
+        for &(x, y) in &[(3.0, 40.0), (-70.0, 2.0), (20.0, -3.0), (-1.0, -1.0)] {
+            let closest = closest_point_on_ellipse(a, b, Point::new(x, y));
+            let on_curve = (closest.x / a).powi(2) + (closest.y / b).powi(2);
+            assert!((on_curve - 1.0).abs() < 1e-6);
+            // no point sampled along the ellipse is closer
+            let best = (0..3600)
+                .map(|i| {
+                    let angle = i as f64 / 3600.0 * std::f64::consts::TAU;
+                    Point::new(a * angle.cos(), b * angle.sin()).distance(Point::new(x, y))
+                })
+                .fold(f64::INFINITY, f64::min);
+            assert!(closest.distance(Point::new(x, y)) <= best + 1e-3);
+        }
+    }
+
+    #[test]
+    fn closest_respects_tolerance() {
+        let candidates = vec![(1, 7.0), (2, 3.0), (3, 4.0)];
+        assert_eq!(closest(candidates.clone().into_iter(), 5.0), Some(2));
+        assert_eq!(closest(candidates.into_iter(), 2.0), None);
+    }
+}
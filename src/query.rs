@@ -0,0 +1,481 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use evalexpr::{
+    build_operator_tree, Context, ContextWithMutableVariables, EvalexprError, HashMapContext, Node,
+    Value,
+};
+use satellite_data::satellites::Satellite;
+
+use crate::columns;
+use crate::fields;
+
+// how many distinct evaluation errors are reported, and satellites named for each
+const LISTED_ERRORS: usize = 3;
+const LISTED_FAILURES: usize = 5;
+
+// character position the error most likely comes from
+pub fn locate(source: &str, error: &EvalexprError) -> Option<usize> {
+    match error {
+        EvalexprError::VariableIdentifierNotFound(name)
+        | EvalexprError::FunctionIdentifierNotFound(name) => find_identifier(source, name),
+        _ => unbalanced(source),
+    }
+}
+
+fn find_identifier(source: &str, name: &str) -> Option<usize> {
+    let is_identifier = |character: char| character.is_alphanumeric() || character == '_';
+    source
+        .match_indices(name)
+        .find(|(start, _)| {
+            let before = source[..*start].chars().next_back();
+            let after = source[start + name.len()..].chars().next();
+            !before.is_some_and(is_identifier) && !after.is_some_and(is_identifier)
+        })
+        .map(|(start, _)| source[..start].chars().count())
+}
+
+// the first closing parenthesis without an opening one, else the first opening
+// parenthesis or double quote left open; a backslash in a string escapes the next
+// character
+fn unbalanced(source: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, character) in source.chars().enumerate() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if quote.is_some() => escaped = true,
+            '"' => quote = if quote.is_some() { None } else { Some(index) },
+            _ if quote.is_some() => {}
+            '(' => open.push(index),
+            ')' if open.pop().is_none() => return Some(index),
+            _ => {}
+        }
+    }
+    quote.or_else(|| open.first().copied())
+}
+
+pub fn describe(source: &str, error: &EvalexprError) -> String {
+    match locate(source, error) {
+        Some(position) => format!("{} (column {})", error, position + 1),
+        None => error.to_string(),
+    }
+}
+
+// an expression parsed once, with its numeric value cached per satellite id;
+// one that does not parse matches nothing and evaluates to 0.0, as does an empty one
+#[derive(Clone, Debug)]
+pub struct Query {
+    source: String,
+    tree: Option<Node>,
+    error: Option<String>,
+    // used by the expression, each once
+    variables: Vec<String>,
+    values: RefCell<HashMap<usize, f64>>,
+    // satellites for which a value the expression uses is unknown
+    unknown: RefCell<HashSet<usize>>,
+    failures: RefCell<BTreeMap<usize, String>>,
+    // of the computed columns the values were cached with
+    generation: u64,
+}
+
+impl Query {
+    pub fn new(source: &str) -> Query {
+        let (tree, error) = if source.trim().is_empty() {
+            (None, None)
+        } else {
+            match build_operator_tree(source) {
+                Ok(tree) => (Some(tree), None),
+                Err(error) => (None, Some(describe(source, &error))),
+            }
+        };
+        let mut variables: Vec<String> = tree
+            .iter()
+            .flat_map(Node::iter_variable_identifiers)
+            .map(String::from)
+            .collect();
+        variables.sort_unstable();
+        variables.dedup();
+        Query {
+            source: source.to_string(),
+            tree,
+            error,
+            variables,
+            values: RefCell::new(HashMap::new()),
+            unknown: RefCell::new(HashSet::new()),
+            failures: RefCell::new(BTreeMap::new()),
+            generation: columns::generation(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // recompiles, dropping the cached values, only when the expression or the
+    // computed columns changed
+    pub fn set_source(&mut self, source: &str) {
+        if self.source != source || self.generation != columns::generation() {
+            *self = Query::new(source);
+        }
+    }
+
+    // the parse error, with its position
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn fail(&self, satellite: &Satellite, error: EvalexprError) {
+        self.failures
+            .borrow_mut()
+            .insert(satellite.id, describe(&self.source, &error));
+    }
+
+    // whether an empty computed column is used, which would fail to evaluate
+    fn uses_empty(&self, context: &HashMapContext) -> bool {
+        self.variables
+            .iter()
+            .any(|variable| matches!(context.get_value(variable), Some(Value::Empty)))
+    }
+
+    // a satellite whose computed columns used are empty does not match
+    pub fn matches(&self, satellite: &Satellite) -> bool {
+        match &self.tree {
+            Some(_) => self.matches_in(&fields::context(satellite), satellite),
+            None => false,
+        }
+    }
+
+    fn matches_in(&self, context: &HashMapContext, satellite: &Satellite) -> bool {
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => return false,
+        };
+        if self.uses_empty(context) {
+            return false;
+        }
+        match tree.eval_boolean_with_context(context) {
+            Ok(matches) => matches,
+            Err(error) => {
+                self.fail(satellite, error);
+                false
+            }
+        }
+    }
+
+    pub fn value(&self, satellite: &Satellite) -> f64 {
+        if let Some(value) = self.values.borrow().get(&satellite.id) {
+            return *value;
+        }
+        match &self.tree {
+            Some(_) => self.value_in(&fields::context(satellite), satellite),
+            None => 0.0,
+        }
+    }
+
+    // evaluates and caches the value in the given context
+    fn value_in(&self, context: &HashMapContext, satellite: &Satellite) -> f64 {
+        let value = match &self.tree {
+            Some(tree) => {
+                let unknown = self
+                    .variables
+                    .iter()
+                    .any(|variable| columns::is_unknown(context, satellite, variable));
+                if unknown {
+                    self.unknown.borrow_mut().insert(satellite.id);
+                }
+                if self.uses_empty(context) {
+                    0.0
+                } else {
+                    match tree.eval_number_with_context(context) {
+                        Ok(value) => value,
+                        Err(error) => {
+                            self.fail(satellite, error);
+                            0.0
+                        }
+                    }
+                }
+            }
+            None => 0.0,
+        };
+        self.values.borrow_mut().insert(satellite.id, value);
+        value
+    }
+
+    // the value, or None when it failed, is not finite or uses a catalogue value or
+    // computed column that is unknown for this satellite; an empty expression is 0.0
+    // as in `value`
+    pub fn known_value(&self, satellite: &Satellite) -> Option<f64> {
+        if self.error.is_some() {
+            return None;
+        }
+        let value = self.value(satellite);
+        let failed = self.failures.borrow().contains_key(&satellite.id);
+        if failed || !value.is_finite() || self.unknown.borrow().contains(&satellite.id) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    // the value as text, for expressions that need not be numbers; "?" when it fails
+    pub fn label(&self, satellite: &Satellite) -> String {
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => return "?".to_string(),
+        };
+        match tree.eval_with_context(&fields::context(satellite)) {
+            Ok(Value::String(text)) => text,
+            Ok(value) => value.to_string(),
+            Err(error) => {
+                self.fail(satellite, error);
+                "?".to_string()
+            }
+        }
+    }
+
+    // evaluation errors among `satellites`, one line per distinct error with the
+    // number of satellites and the first few names; empty when none failed
+    pub fn failure_report(&self, satellites: &[Satellite]) -> String {
+        let failures = self.failures.borrow();
+        let mut errors: Vec<(&str, Vec<&str>)> = Vec::new();
+        let mut count = 0;
+        for satellite in satellites {
+            let message = match failures.get(&satellite.id) {
+                Some(message) => message.as_str(),
+                None => continue,
+            };
+            count += 1;
+            match errors.iter_mut().find(|(error, _)| *error == message) {
+                Some((_, names)) => names.push(&satellite.name),
+                None => errors.push((message, vec![&satellite.name])),
+            }
+        }
+        if count == 0 {
+            return String::new();
+        }
+        let mut report = format!("{} of {} satellites failed:", count, satellites.len());
+        if errors.len() > LISTED_ERRORS {
+            report.push_str(&format!(" ({} different errors)", errors.len()));
+        }
+        for (message, names) in errors.into_iter().take(LISTED_ERRORS) {
+            let mut listed = names[..names.len().min(LISTED_FAILURES)].join(", ");
+            if names.len() > LISTED_FAILURES {
+                listed.push_str(", …");
+            }
+            report.push_str(&format!("\n{} ×{} ({})", message, names.len(), listed));
+        }
+        report
+    }
+
+    // the parse error, else the evaluation errors among `satellites`
+    pub fn status(&self, satellites: &[Satellite]) -> String {
+        match &self.error {
+            Some(error) => format!("bad expression: {}", error),
+            None => self.failure_report(satellites),
+        }
+    }
+
+    // returns the value together with its propagated uncertainty, or None for the
+    // uncertainty when one of the measured values used is unknown; values reported
+    // without an uncertainty count as exact
+    pub fn value_with_error(&self, satellite: &Satellite) -> (f64, Option<f64>) {
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => return (0.0, Some(0.0)),
+        };
+        let value = self.value(satellite);
+        let mut context = fields::context(satellite);
+
+        // linear propagation, each term is estimated by evaluating at value ± error
+        let mut variance = 0.0;
+        for variable in &self.variables {
+            let param = match fields::field(variable).and_then(|field| field.measured(satellite)) {
+                Some(param) => param,
+                None => continue,
+            };
+            if param.error < 0.0 {
+                return (value, None);
+            }
+            if param.error == 0.0 {
+                continue;
+            }
+            context
+                .set_value(variable.to_string(), (param.data + param.error).into())
+                .unwrap();
+            let upper = tree.eval_number_with_context(&context).unwrap_or(value);
+            context
+                .set_value(variable.to_string(), (param.data - param.error).into())
+                .unwrap();
+            let lower = tree.eval_number_with_context(&context).unwrap_or(value);
+            context
+                .set_value(variable.to_string(), param.data.into())
+                .unwrap();
+            variance += ((upper - lower) / 2.0).powi(2);
+        }
+        (value, Some(variance.sqrt()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::columns::ColumnSet;
+    use crate::test_support::{satellite, SatelliteBuilder, UNKNOWN};
+    use evalexpr::eval_float_with_context;
+    use satellite_data::satellites::MajorBody;
+
+    fn catalogue(size: usize) -> Vec<Satellite> {
+        (0..size)
+            .map(|id| {
+                let major_body = MajorBody::ALL[id % MajorBody::ALL.len()];
+                satellite(id, major_body, 1000.0 + id as f64 * 37.0)
+                    .eccentricity((id % 97) as f64 / 100.0)
+                    .inclination((id % 180) as f64)
+                    .ascending_node((id % 360) as f64)
+                    .gm(id as f64)
+                    .density(2.0)
+                    .magnitude(20.0)
+                    .albedo(0.1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn values_are_cached_until_the_source_changes() {
+        let satellites = catalogue(3);
+        let mut query = Query::new("a * 2");
+        assert_eq!(query.value(&satellites[1]), 2074.0);
+        assert_eq!(query.values.borrow().len(), 1);
+        query.set_source("a * 2");
+        assert_eq!(query.values.borrow().len(), 1);
+        query.set_source("i > 1");
+        assert!(query.values.borrow().is_empty());
+        assert!(query.matches(&satellites[2]));
+        assert!(!query.matches(&satellites[1]));
+        let broken = Query::new("a * (");
+        assert!(!broken.matches(&satellites[0]));
+        assert_eq!(broken.value(&satellites[0]), 0.0);
+    }
+
+    // a column set of its own, the shared one is left alone for the other tests
+    #[test]
+    fn computed_columns_on_unknown_values_are_unknown() {
+        let mut set = ColumnSet::default();
+        set.define(&["hill = a * (gm / parent_gm) ^ 0.5".to_string()])
+            .unwrap();
+        let known = satellite(0, MajorBody::Saturn, 1000.0).gm(4.0);
+        let unknown = satellite(1, MajorBody::Saturn, 1000.0).gm(UNKNOWN);
+        let context = |satellite: &Satellite, set: &mut ColumnSet| {
+            let mut context = fields::context(satellite);
+            set.extend(&mut context, satellite);
+            context
+        };
+        let (known_context, unknown_context) =
+            (context(&known, &mut set), context(&unknown, &mut set));
+
+        // neither matches nor counts as a failure
+        let hill = Query::new("hill");
+        hill.value_in(&known_context, &known);
+        hill.value_in(&unknown_context, &unknown);
+        assert!(hill.known_value(&known).is_some());
+        assert_eq!(hill.known_value(&unknown), None);
+        let filter = Query::new("hill > 0");
+        assert!(filter.matches_in(&known_context, &known));
+        assert!(!filter.matches_in(&unknown_context, &unknown));
+        assert_eq!(filter.failure_report(&[known, unknown]), "");
+    }
+
+    #[test]
+    fn errors_are_located_and_counted() {
+        let satellites = catalogue(8);
+        let broken = Query::new("(a > 1 && e < 2");
+        assert!(broken.error().unwrap().ends_with("(column 1)"));
+        assert!(broken.status(&satellites).starts_with("bad expression: "));
+        assert_eq!(unbalanced("a > (1))"), Some(7));
+        assert_eq!(unbalanced("name == \"Io"), Some(8));
+        assert_eq!(
+            unbalanced("name == \"say \\\"moon\\\"\" && (a > 1"),
+            Some(26)
+        );
+        assert_eq!(unbalanced("name == \"a\\\\\" && a > 1)"), Some(22));
+        assert_eq!(unbalanced("name == \"say \\\"moon"), Some(8));
+
+        let unknown = Query::new("a + ab + abc");
+        assert_eq!(unknown.error(), None);
+        unknown.value(&satellites[0]);
+        let report = unknown.failure_report(&satellites);
+        assert!(report.starts_with("1 of 8 satellites failed:\n"));
+        assert!(report.ends_with("(column 5) ×1 (S/0)"));
+
+        // the report covers only the satellites given
+        let names = Query::new("a + foo");
+        satellites[..3].iter().for_each(|satellite| {
+            names.value(satellite);
+        });
+        assert!(names
+            .status(&satellites)
+            .starts_with("3 of 8 satellites failed:"));
+        assert!(names.status(&satellites).ends_with("×3 (S/0, S/1, S/2)"));
+        assert_eq!(Query::new(" ").status(&satellites), "");
+    }
+
+    #[test]
+    fn uncertainties_skip_exact_values() {
+        let moon = satellite(0, MajorBody::Saturn, 1000.0)
+            .radius((100.0, 2.0))
+            .albedo(0.5)
+            .magnitude(UNKNOWN);
+        // the albedo has no reported error, so only the radius contributes
+        let (value, error) = Query::new("radius * albedo").value_with_error(&moon);
+        assert_eq!(value, 50.0);
+        assert!((error.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(Query::new("a").value_with_error(&moon), (1000.0, Some(0.0)));
+
+        // an unknown value has no uncertainty and no position on the graph
+        let magnitude = Query::new("magnitude + 1");
+        assert_eq!(magnitude.value_with_error(&moon), (1.0, None));
+        assert_eq!(magnitude.known_value(&moon), None);
+        assert_eq!(Query::new("radius").known_value(&moon), Some(100.0));
+    }
+
+    // compiling once and caching beats parsing per call; cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn benchmark_10k_catalogue() {
+        let satellites = catalogue(10_000);
+        let expression = "a * (1 + e) / 1000";
+        let frames = 10;
+
+        let start = Instant::now();
+        let mut uncompiled = 0.0;
+        for _ in 0..frames {
+            for satellite in &satellites {
+                let context = fields::context(satellite);
+                uncompiled += eval_float_with_context(expression, &context).unwrap();
+            }
+        }
+        let uncompiled_time = start.elapsed();
+
+        let start = Instant::now();
+        let query = Query::new(expression);
+        let mut compiled = 0.0;
+        for _ in 0..frames {
+            for satellite in &satellites {
+                compiled += query.value(satellite);
+            }
+        }
+        let compiled_time = start.elapsed();
+
+        assert_eq!(uncompiled, compiled);
+        assert!(
+            compiled_time < uncompiled_time,
+            "compiled and cached {:?}, parsed per call {:?}",
+            compiled_time,
+            uncompiled_time
+        );
+    }
+}
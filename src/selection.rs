@@ -0,0 +1,342 @@
+use druid::kurbo::{Point, Rect};
+use druid::{Data, Modifiers};
+
+use crate::picking::OrbitShape;
+
+// number of vertices used to approximate an orbit when testing it against a shape
+const ORBIT_SAMPLES: usize = 128;
+
+#[derive(Clone, Copy, Data, PartialEq, Debug)]
+pub enum SelectionTool {
+    Rectangle,
+    Lasso,
+    Polygon,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionMode {
+    Replace,
+    Add,
+    Subtract,
+    Toggle,
+}
+
+impl SelectionMode {
+    pub fn from_modifiers(mods: Modifiers) -> SelectionMode {
+        if mods.ctrl() {
+            SelectionMode::Toggle
+        } else if mods.alt() {
+            SelectionMode::Subtract
+        } else if mods.shift() {
+            SelectionMode::Add
+        } else {
+            SelectionMode::Replace
+        }
+    }
+}
+
+// combines the current selection with the satellites hit by a gesture, keeping the
+// order in which satellites were selected
+pub fn apply(current: &[usize], hits: &[usize], mode: SelectionMode) -> Vec<usize> {
+    match mode {
+        SelectionMode::Replace => {
+            let mut selection = Vec::new();
+            for id in hits {
+                if !selection.contains(id) {
+                    selection.push(*id);
+                }
+            }
+            selection
+        }
+        SelectionMode::Add => {
+            let mut selection = current.to_vec();
+            for id in hits {
+                if !selection.contains(id) {
+                    selection.push(*id);
+                }
+            }
+            selection
+        }
+        SelectionMode::Subtract => current
+            .iter()
+            .copied()
+            .filter(|id| !hits.contains(id))
+            .collect(),
+        SelectionMode::Toggle => {
+            let mut selection: Vec<usize> = current
+                .iter()
+                .copied()
+                .filter(|id| !hits.contains(id))
+                .collect();
+            for id in hits {
+                if !current.contains(id) && !selection.contains(id) {
+                    selection.push(*id);
+                }
+            }
+            selection
+        }
+    }
+}
+
+// an in-progress selection: a rectangle from the first to the last point, a freehand
+// lasso through all points or a polygon whose last point follows the cursor until the
+// next vertex is placed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gesture {
+    pub points: Vec<Point>,
+    pub tool: SelectionTool,
+    pub mode: SelectionMode,
+}
+
+impl Gesture {
+    pub fn new(start: Point, tool: SelectionTool, mode: SelectionMode) -> Gesture {
+        let points = match tool {
+            SelectionTool::Polygon => vec![start, start],
+            _ => vec![start],
+        };
+        Gesture { points, tool, mode }
+    }
+
+    pub fn drag_to(&mut self, point: Point) {
+        match self.tool {
+            SelectionTool::Rectangle => {
+                self.points.truncate(1);
+                self.points.push(point);
+            }
+            SelectionTool::Lasso => self.points.push(point),
+            SelectionTool::Polygon => {
+                let last = self.points.len() - 1;
+                self.points[last] = point;
+            }
+        }
+    }
+
+    pub fn add_vertex(&mut self, point: Point) {
+        self.drag_to(point);
+        if self.tool == SelectionTool::Polygon {
+            self.points.push(point);
+        }
+    }
+
+    pub fn is_click(&self) -> bool {
+        let start = self.points[0];
+        self.points.iter().all(|point| point.distance(start) < 3.0)
+    }
+
+    // a plain click selects a `click_size` square around the cursor
+    pub fn polygon(&self, click_size: f64) -> Vec<Point> {
+        let start = self.points[0];
+        if self.is_click() {
+            rect_polygon(Rect::from_center_size(start, (click_size, click_size)))
+        } else if self.tool == SelectionTool::Rectangle {
+            rect_polygon(Rect::from_points(start, self.points[self.points.len() - 1]))
+        } else {
+            self.points.clone()
+        }
+    }
+}
+
+pub fn rect_polygon(rect: Rect) -> Vec<Point> {
+    let rect = rect.abs();
+    vec![
+        Point::new(rect.x0, rect.y0),
+        Point::new(rect.x1, rect.y0),
+        Point::new(rect.x1, rect.y1),
+        Point::new(rect.x0, rect.y1),
+    ]
+}
+
+pub fn polygon_bounds(polygon: &[Point]) -> Rect {
+    let mut points = polygon.iter();
+    let first = match points.next() {
+        Some(point) => *point,
+        None => return Rect::ZERO,
+    };
+    points.fold(Rect::from_points(first, first), |bounds, point| {
+        bounds.union_pt(*point)
+    })
+}
+
+// even-odd rule, the polygon is implicitly closed
+pub fn contains(polygon: &[Point], point: Point) -> bool {
+    let mut inside = false;
+    for (index, a) in polygon.iter().enumerate() {
+        let b = polygon[(index + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn segments_intersect(a0: Point, a1: Point, b0: Point, b1: Point) -> bool {
+    let cross = |o: Point, p: Point, q: Point| (p - o).cross(q - o);
+    let d0 = cross(b0, b1, a0);
+    let d1 = cross(b0, b1, a1);
+    let d2 = cross(a0, a1, b0);
+    let d3 = cross(a0, a1, b1);
+    (d0 > 0.0) != (d1 > 0.0) && (d2 > 0.0) != (d3 > 0.0)
+}
+
+// true when some part of the orbit line lies inside the polygon
+pub fn orbit_touches(shape: &OrbitShape, polygon: &[Point]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    let (sin, cos) = shape.rotation.sin_cos();
+    let vertices: Vec<Point> = (0..ORBIT_SAMPLES)
+        .map(|i| {
+            let angle = i as f64 / ORBIT_SAMPLES as f64 * std::f64::consts::PI * 2.0;
+            let x = shape.radii.x * angle.cos();
+            let y = shape.radii.y * angle.sin();
+            Point::new(
+                shape.center.x + x * cos - y * sin,
+                shape.center.y + x * sin + y * cos,
+            )
+        })
+        .collect();
+    if vertices.iter().any(|vertex| contains(polygon, *vertex)) {
+        return true;
+    }
+    for (index, a0) in vertices.iter().enumerate() {
+        let a1 = vertices[(index + 1) % vertices.len()];
+        for (index, b0) in polygon.iter().enumerate() {
+            let b1 = polygon[(index + 1) % polygon.len()];
+            if segments_intersect(*a0, a1, *b0, b1) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_modes() {
+        let current = vec![1, 2, 3];
+        assert_eq!(apply(&current, &[3, 4], SelectionMode::Replace), vec![3, 4]);
+        assert_eq!(
+            apply(&current, &[3, 4], SelectionMode::Add),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(
+            apply(&current, &[3, 4], SelectionMode::Toggle),
+            vec![1, 2, 4]
+        );
+        assert_eq!(
+            apply(&current, &[], SelectionMode::Replace),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn apply_subtract() {
+        assert_eq!(
+            apply(&[1, 2, 3], &[2, 5], SelectionMode::Subtract),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn gesture_shapes() {
+        let mut gesture = Gesture::new(
+            Point::new(10.0, 10.0),
+            SelectionTool::Rectangle,
+            SelectionMode::Replace,
+        );
+        assert!(gesture.is_click());
+        assert_eq!(
+            polygon_bounds(&gesture.polygon(20.0)),
+            Rect::new(0.0, 0.0, 20.0, 20.0)
+        );
+        gesture.drag_to(Point::new(50.0, 30.0));
+        gesture.drag_to(Point::new(40.0, 60.0));
+        assert_eq!(gesture.points.len(), 2);
+        assert_eq!(
+            polygon_bounds(&gesture.polygon(20.0)),
+            Rect::new(10.0, 10.0, 40.0, 60.0)
+        );
+
+        let mut lasso = Gesture::new(
+            Point::new(0.0, 0.0),
+            SelectionTool::Lasso,
+            SelectionMode::Replace,
+        );
+        lasso.drag_to(Point::new(50.0, 0.0));
+        lasso.drag_to(Point::new(0.0, 50.0));
+        assert_eq!(lasso.polygon(20.0).len(), 3);
+    }
+
+    #[test]
+    fn polygon_follows_cursor() {
+        let mut gesture = Gesture::new(
+            Point::new(0.0, 0.0),
+            SelectionTool::Polygon,
+            SelectionMode::Add,
+        );
+        gesture.drag_to(Point::new(30.0, 5.0));
+        gesture.add_vertex(Point::new(40.0, 0.0));
+        gesture.drag_to(Point::new(20.0, 40.0));
+        assert_eq!(
+            gesture.polygon(20.0),
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(40.0, 0.0),
+                Point::new(20.0, 40.0)
+            ]
+        );
+        assert!(contains(&gesture.polygon(20.0), Point::new(20.0, 10.0)));
+    }
+
+    #[test]
+    fn point_in_polygon() {
+        let square = rect_polygon(Rect::new(0.0, 0.0, 10.0, 10.0));
+        assert!(contains(&square, Point::new(5.0, 5.0)));
+        assert!(!contains(&square, Point::new(15.0, 5.0)));
+        // concave "L" shape
+        let lasso = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 2.0),
+            Point::new(2.0, 2.0),
+            Point::new(2.0, 10.0),
+            Point::new(0.0, 10.0),
+        ];
+        assert!(contains(&lasso, Point::new(1.0, 8.0)));
+        assert!(!contains(&lasso, Point::new(6.0, 6.0)));
+        assert_eq!(polygon_bounds(&lasso), Rect::new(0.0, 0.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn orbit_against_box() {
+        let orbit = OrbitShape {
+            center: Point::new(0.0, 0.0),
+            radii: (100.0, 50.0).into(),
+            rotation: 0.0,
+        };
+        // box on the orbit line
+        assert!(orbit_touches(
+            &orbit,
+            &rect_polygon(Rect::new(95.0, -5.0, 105.0, 5.0))
+        ));
+        // box inside the orbit but away from its line
+        assert!(!orbit_touches(
+            &orbit,
+            &rect_polygon(Rect::new(-5.0, -5.0, 5.0, 5.0))
+        ));
+        // box far outside
+        assert!(!orbit_touches(
+            &orbit,
+            &rect_polygon(Rect::new(200.0, 200.0, 210.0, 210.0))
+        ));
+        // box containing the whole orbit
+        let around = rect_polygon(Rect::new(-200.0, -200.0, 200.0, 200.0));
+        assert!(orbit_touches(&orbit, &around));
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use druid::kurbo::{Point, Rect};
+
+// items covering more cells than this are kept in a separate list and always tested
+const MAX_CELLS_PER_ITEM: i64 = 256;
+
+// uniform grid over screen space bounding boxes, the keys are usually positions in
+// the displayed database
+pub struct SpatialIndex {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    large: Vec<usize>,
+    bounds: HashMap<usize, Rect>,
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f64) -> SpatialIndex {
+        SpatialIndex {
+            cell_size,
+            cells: HashMap::new(),
+            large: Vec::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.large.clear();
+        self.bounds.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    fn cell_range(&self, area: Rect) -> ((i64, i64), (i64, i64)) {
+        let cell = |value: f64| (value / self.cell_size).floor() as i64;
+        (
+            (cell(area.x0), cell(area.y0)),
+            (cell(area.x1), cell(area.y1)),
+        )
+    }
+
+    pub fn insert(&mut self, key: usize, bounds: Rect) {
+        let bounds = bounds.abs();
+        if !(bounds.x0.is_finite()
+            && bounds.y0.is_finite()
+            && bounds.x1.is_finite()
+            && bounds.y1.is_finite())
+        {
+            return;
+        }
+        self.bounds.insert(key, bounds);
+        let ((x0, y0), (x1, y1)) = self.cell_range(bounds);
+        if cell_count((x0, y0), (x1, y1)) > MAX_CELLS_PER_ITEM {
+            self.large.push(key);
+            return;
+        }
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.cells.entry((x, y)).or_default().push(key);
+            }
+        }
+    }
+
+    // sorted keys of all items whose bounds intersect `area`
+    pub fn query(&self, area: Rect) -> Vec<usize> {
+        let area = area.abs();
+        let mut keys: Vec<usize> = Vec::new();
+        let ((x0, y0), (x1, y1)) = self.cell_range(area);
+        if cell_count((x0, y0), (x1, y1)) > self.cells.len() as i64 {
+            keys.extend(self.cells.values().flatten());
+        } else {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    if let Some(cell) = self.cells.get(&(x, y)) {
+                        keys.extend(cell);
+                    }
+                }
+            }
+        }
+        keys.extend(&self.large);
+        keys.sort_unstable();
+        keys.dedup();
+        keys.retain(|key| {
+            self.bounds
+                .get(key)
+                .map_or(false, |bounds| overlaps(*bounds, area))
+        });
+        keys
+    }
+
+    pub fn query_point(&self, point: Point, radius: f64) -> Vec<usize> {
+        self.query(Rect::from_center_size(point, (2.0 * radius, 2.0 * radius)))
+    }
+}
+
+fn cell_count(first: (i64, i64), last: (i64, i64)) -> i64 {
+    let columns = last.0.saturating_sub(first.0).saturating_add(1);
+    let rows = last.1.saturating_sub(first.1).saturating_add(1);
+    columns.saturating_mul(rows)
+}
+
+// unlike `Rect::intersect` this also accepts zero sized rectangles and touching edges
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.x0 <= b.x1 && a.x1 >= b.x0 && a.y0 <= b.y1 && a.y1 >= b.y0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_finds_intersecting_items() {
+        let mut index = SpatialIndex::new(10.0);
+        index.insert(0, Rect::new(0.0, 0.0, 5.0, 5.0));
+        index.insert(1, Rect::new(50.0, 50.0, 60.0, 60.0));
+        index.insert(2, Rect::new(-1000.0, -1000.0, 1000.0, 1000.0));
+        index.insert(3, Rect::new(58.0, 8.0, 58.0, 8.0));
+        assert_eq!(index.query(Rect::new(1.0, 1.0, 2.0, 2.0)), vec![0, 2]);
+        assert_eq!(index.query(Rect::new(55.0, 0.0, 70.0, 70.0)), vec![1, 2, 3]);
+        assert_eq!(index.query_point(Point::new(200.0, 200.0), 3.0), vec![2]);
+        assert_eq!(index.len(), 4);
+    }
+
+    #[test]
+    fn huge_query_area_returns_everything() {
+        let mut index = SpatialIndex::new(1.0);
+        for key in 0..10 {
+            let origin = Point::new(key as f64 * 3.0, 0.0);
+            index.insert(key, Rect::from_origin_size(origin, (1.0, 1.0)));
+        }
+        let all = index.query(Rect::new(-1e9, -1e9, 1e9, 1e9));
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,435 @@
+use std::cmp::Ordering;
+
+use druid::kurbo::{Line, Rect};
+use druid::piet::{Color, Text, TextLayoutBuilder};
+use druid::{
+    BoxConstraints, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
+    Point, RenderContext, Size, UpdateCtx, Widget,
+};
+use satellite_data::{database::Database, satellites::Satellite};
+
+use crate::astronomy_canvas::AstronomyCanvasData;
+use crate::query::Query;
+use crate::table::compare_values;
+
+const GROUP_WIDTH: f64 = 120.0;
+const CELL_WIDTH: f64 = 130.0;
+const ROW_HEIGHT: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Mean,
+    Median,
+    Min,
+    Max,
+    Std,
+}
+
+pub const AGGREGATES: [(&str, Aggregate); 7] = [
+    ("count", Aggregate::Count),
+    ("sum", Aggregate::Sum),
+    ("mean", Aggregate::Mean),
+    ("median", Aggregate::Median),
+    ("min", Aggregate::Min),
+    ("max", Aggregate::Max),
+    ("std", Aggregate::Std),
+];
+
+impl Aggregate {
+    // None when there are no values to aggregate, or too few for the spread
+    pub fn apply(&self, values: &mut [f64]) -> Option<f64> {
+        let count = values.len() as f64;
+        if values.is_empty() {
+            return if *self == Aggregate::Count {
+                Some(0.0)
+            } else {
+                None
+            };
+        }
+        let sum: f64 = values.iter().sum();
+        match self {
+            Aggregate::Count => Some(count),
+            Aggregate::Sum => Some(sum),
+            Aggregate::Mean => Some(sum / count),
+            Aggregate::Median => {
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                let middle = values.len() / 2;
+                if values.len() % 2 == 1 {
+                    Some(values[middle])
+                } else {
+                    Some((values[middle - 1] + values[middle]) / 2.0)
+                }
+            }
+            Aggregate::Min => values.iter().copied().reduce(f64::min),
+            Aggregate::Max => values.iter().copied().reduce(f64::max),
+            Aggregate::Std if values.len() < 2 => None,
+            Aggregate::Std => {
+                let mean = sum / count;
+                let variance = values
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>();
+                Some((variance / (count - 1.0)).sqrt())
+            }
+        }
+    }
+}
+
+// one column of the summary: `count`, or an aggregate of an expression such as
+// `mean(albedo)`; satellites for which the expression is unknown are left out
+#[derive(Clone, Debug)]
+pub struct Term {
+    pub label: String,
+    pub aggregate: Aggregate,
+    pub argument: Option<Query>,
+}
+
+impl Term {
+    pub fn value(&self, satellites: &[&Satellite]) -> Option<f64> {
+        let mut values: Vec<f64> = match &self.argument {
+            Some(argument) => satellites
+                .iter()
+                .filter_map(|satellite| argument.known_value(satellite))
+                .collect(),
+            None => vec![0.0; satellites.len()],
+        };
+        self.aggregate.apply(&mut values)
+    }
+}
+
+// splits at the commas outside parentheses and strings
+fn split_terms(source: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, character) in source.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                terms.push(&source[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push(&source[start..]);
+    terms
+}
+
+// parses a comma separated list of aggregates such as `count, mean(a), max(radius)`
+pub fn parse_terms(source: &str) -> Result<Vec<Term>, String> {
+    let mut terms = Vec::new();
+    for term in split_terms(source) {
+        let term = term.trim();
+        if term.is_empty() {
+            continue;
+        }
+        let (name, argument) = match term.find('(') {
+            Some(open) if term.ends_with(')') => {
+                (term[..open].trim(), Some(&term[open + 1..term.len() - 1]))
+            }
+            _ => (term, None),
+        };
+        let aggregate = AGGREGATES
+            .iter()
+            .find(|(aggregate, _)| *aggregate == name)
+            .map(|(_, aggregate)| *aggregate)
+            .ok_or_else(|| {
+                format!(
+                    "unknown aggregate \"{}\", expected count, sum, mean, median, min, max or std",
+                    name
+                )
+            })?;
+        let argument = match argument {
+            Some(argument) => {
+                let query = Query::new(argument);
+                if let Some(error) = query.error() {
+                    return Err(format!("{}: {}", term, error));
+                }
+                Some(query)
+            }
+            None if aggregate == Aggregate::Count => None,
+            None => return Err(format!("{} needs an expression, as in {}(a)", name, name)),
+        };
+        terms.push(Term {
+            label: term.to_string(),
+            aggregate,
+            argument,
+        });
+    }
+    Ok(terms)
+}
+
+// numbers in numeric order, before any text
+fn compare_groups(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => compare_values(Some(a), Some(b), false),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+// rows of group name and the value of each term, followed by a row for all the
+// satellites when they are grouped
+pub fn summarise(
+    satellites: &[Satellite],
+    terms: &[Term],
+    group_by: &Query,
+) -> Vec<(String, Vec<Option<f64>>)> {
+    let row = |name: String, members: &[&Satellite]| {
+        let values = terms.iter().map(|term| term.value(members)).collect();
+        (name, values)
+    };
+    let all: Vec<&Satellite> = satellites.iter().collect();
+    if group_by.source().trim().is_empty() {
+        return vec![row("all".to_string(), &all)];
+    }
+    let mut groups: Vec<(String, Vec<&Satellite>)> = Vec::new();
+    for satellite in satellites {
+        let name = group_by.label(satellite);
+        match groups.iter_mut().find(|(group, _)| *group == name) {
+            Some((_, members)) => members.push(satellite),
+            None => groups.push((name, vec![satellite])),
+        }
+    }
+    groups.sort_by(|(a, _), (b, _)| compare_groups(a, b));
+    let mut rows: Vec<(String, Vec<Option<f64>>)> = groups
+        .iter()
+        .map(|(name, members)| row(name.clone(), members))
+        .collect();
+    rows.push(row("all".to_string(), &all));
+    rows
+}
+
+pub fn format_value(value: f64) -> String {
+    let magnitude = value.abs();
+    if value == value.round() && magnitude < 1e9 {
+        format!("{}", value)
+    } else if !(1e-2..1e5).contains(&magnitude) {
+        format!("{:.3e}", value)
+    } else {
+        format!("{:.3}", value)
+    }
+}
+
+// aggregates of the filtered satellites, grouped by an expression
+pub struct SummaryPanel {
+    database: Database,
+    rows: Vec<(String, Vec<Option<f64>>)>,
+    labels: Vec<String>,
+    status: String,
+}
+
+impl SummaryPanel {
+    pub fn new(database: Database) -> SummaryPanel {
+        SummaryPanel {
+            database,
+            rows: Vec::new(),
+            labels: Vec::new(),
+            status: String::new(),
+        }
+    }
+
+    fn rebuild(&mut self, data: &AstronomyCanvasData) {
+        self.rows.clear();
+        self.labels.clear();
+        let terms = match parse_terms(&data.summary_terms) {
+            Ok(terms) => terms,
+            Err(error) => {
+                self.status = error;
+                return;
+            }
+        };
+        let group_by = Query::new(&data.group_by);
+        let satellites: Vec<Satellite> = data
+            .displayed_satellites
+            .0
+            .iter()
+            .filter_map(|wrapper| self.database.get_satellite_by_id(wrapper.satellite))
+            .collect();
+        self.rows = summarise(&satellites, &terms, &group_by);
+        self.labels = terms.into_iter().map(|term| term.label).collect();
+        self.status = match group_by.error() {
+            Some(_) => format!("group by: {}", group_by.status(&satellites)),
+            None => group_by.failure_report(&satellites),
+        };
+    }
+
+    fn draw_text(&self, ctx: &mut PaintCtx, text: String, color: Color, origin: Point) {
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .text_color(color)
+            .build()
+            .unwrap();
+        ctx.draw_text(&layout, origin);
+    }
+}
+
+impl Widget<AstronomyCanvasData> for SummaryPanel {
+    fn event(
+        &mut self,
+        _ctx: &mut EventCtx,
+        _event: &Event,
+        _data: &mut AstronomyCanvasData,
+        _env: &Env,
+    ) {
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rebuild(data);
+        }
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &AstronomyCanvasData,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if !old_data
+            .displayed_satellites
+            .same(&data.displayed_satellites)
+            || old_data.summary_terms != data.summary_terms
+            || old_data.group_by != data.group_by
+            || !old_data.columns.same(&data.columns)
+        {
+            self.rebuild(data);
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _layout_ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AstronomyCanvasData,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &AstronomyCanvasData, _env: &Env) {
+        let size = ctx.size();
+        ctx.clip(size.to_rect());
+        ctx.fill(size.to_rect(), &Color::grey(0.1));
+
+        let left = |index: usize| GROUP_WIDTH + index as f64 * CELL_WIDTH;
+        ctx.fill(
+            Rect::new(0.0, 0.0, size.width, ROW_HEIGHT),
+            &Color::grey(0.25),
+        );
+        for (index, label) in self.labels.iter().enumerate() {
+            self.draw_text(
+                ctx,
+                label.clone(),
+                Color::WHITE,
+                Point::new(left(index) + 4.0, 2.0),
+            );
+        }
+        for (row, (group, values)) in self.rows.iter().enumerate() {
+            let y = (row + 1) as f64 * ROW_HEIGHT;
+            // the last row is the total over every group
+            let total = row + 1 == self.rows.len() && self.rows.len() > 1;
+            if total {
+                ctx.fill(
+                    Rect::new(0.0, y, size.width, y + ROW_HEIGHT),
+                    &Color::grey(0.2),
+                );
+            } else if row % 2 == 1 {
+                ctx.fill(
+                    Rect::new(0.0, y, size.width, y + ROW_HEIGHT),
+                    &Color::grey(0.13),
+                );
+            }
+            self.draw_text(ctx, group.clone(), Color::WHITE, Point::new(4.0, y + 2.0));
+            for (index, value) in values.iter().enumerate() {
+                let text = value.map_or("–".to_string(), format_value);
+                self.draw_text(
+                    ctx,
+                    text,
+                    Color::WHITE,
+                    Point::new(left(index) + 4.0, y + 2.0),
+                );
+            }
+        }
+        for index in 0..self.labels.len() {
+            ctx.stroke(
+                Line::new((left(index), 0.0), (left(index), size.height)),
+                &Color::grey(0.3),
+                1.0,
+            );
+        }
+        if !self.status.is_empty() {
+            let y = (self.rows.len() + 1) as f64 * ROW_HEIGHT + 4.0;
+            self.draw_text(
+                ctx,
+                self.status.clone(),
+                Color::rgb(1.0, 0.5, 0.4),
+                Point::new(4.0, y),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{satellite, SatelliteBuilder, UNKNOWN};
+    use satellite_data::satellites::MajorBody;
+
+    // a negative albedo is unknown
+    fn moon(id: usize, major_body: MajorBody, radius: f64, albedo: f64) -> Satellite {
+        satellite(id, major_body, 1000.0 * (id + 1) as f64)
+            .radius((radius, 1.0))
+            .magnitude(UNKNOWN)
+            .albedo(if albedo < 0.0 {
+                UNKNOWN
+            } else {
+                (albedo, 0.01)
+            })
+    }
+
+    #[test]
+    fn aggregates_by_group_skip_unknown_values() {
+        let satellites = vec![
+            moon(0, MajorBody::Uranus, 500.0, 0.2),
+            moon(1, MajorBody::Uranus, 700.0, 0.4),
+            moon(2, MajorBody::Uranus, 20.0, -1.0),
+            moon(3, MajorBody::Saturn, 100.0, 0.5),
+        ];
+        let terms = parse_terms("count, mean(albedo), median(radius), max(a / 1000)").unwrap();
+        let rows = summarise(&satellites, &terms, &Query::new("mb"));
+        let names: Vec<&str> = rows.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Saturn", "Uranus", "all"]);
+        let uranus = &rows[1].1;
+        assert_eq!(uranus[0], Some(3.0));
+        assert!((uranus[1].unwrap() - 0.3).abs() < 1e-12);
+        assert_eq!(uranus[2], Some(500.0));
+        assert_eq!(uranus[3], Some(3.0));
+        assert_eq!(rows[2].1[0], Some(4.0));
+
+        let rows = summarise(&satellites, &terms, &Query::new("radius > 200"));
+        assert_eq!(rows[0].0, "false");
+        assert_eq!(summarise(&satellites, &terms, &Query::new("")).len(), 1);
+        assert_eq!(Aggregate::Std.apply(&mut [1.0]), None);
+        assert_eq!(Aggregate::Mean.apply(&mut []), None);
+        assert!(parse_terms("mean").is_err());
+        assert!(parse_terms("average(a)").is_err());
+        assert!(parse_terms("max(a, ").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use druid::kurbo::{Point, Rect, Size};
+use druid::Data;
+use satellite_data::satellites::{MajorBody, Satellite};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SystemView {
+    All,
+    Single(MajorBody),
+    SmallMultiples,
+}
+
+impl Data for SystemView {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl SystemView {
+    pub fn includes(&self, satellite: &Satellite) -> bool {
+        match self {
+            SystemView::Single(body) => satellite.major_body == *body,
+            _ => true,
+        }
+    }
+}
+
+// one system of the small multiples view, `scale` is in km per pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tile {
+    pub body: MajorBody,
+    pub rect: Rect,
+    pub center: Point,
+    pub scale: f64,
+}
+
+// largest apoapsis among the satellites, in km
+pub fn extent<'a>(satellites: impl Iterator<Item = &'a Satellite>) -> f64 {
+    satellites
+        .map(|satellite| {
+            let orbit = satellite.orbital_params;
+            orbit.major_semiaxis * (1.0 + orbit.eccentricity)
+        })
+        .fold(0.0, f64::max)
+}
+
+// tiles every system with satellites in a grid filling `size`, each one scaled so
+// that its widest orbit fits
+pub fn layout_tiles<'a>(
+    size: Size,
+    satellites: impl Iterator<Item = &'a Satellite> + Clone,
+) -> Vec<Tile> {
+    let bodies: Vec<MajorBody> = MajorBody::ALL
+        .iter()
+        .copied()
+        .filter(|body| {
+            satellites
+                .clone()
+                .any(|satellite| satellite.major_body == *body)
+        })
+        .collect();
+    if bodies.is_empty() {
+        return Vec::new();
+    }
+    let columns = (bodies.len() as f64).sqrt().ceil() as usize;
+    let rows = (bodies.len() + columns - 1) / columns;
+    let width = size.width / columns as f64;
+    let height = size.height / rows as f64;
+    bodies
+        .into_iter()
+        .enumerate()
+        .map(|(index, body)| {
+            let origin = Point::new(
+                (index % columns) as f64 * width,
+                (index / columns) as f64 * height,
+            );
+            let rect = Rect::from_origin_size(origin, (width, height));
+            let extent = extent(
+                satellites
+                    .clone()
+                    .filter(|satellite| satellite.major_body == body),
+            );
+            // leave room for the title at the top of the tile
+            let radius = (width.min(height - 20.0) / 2.0 * 0.9).max(1.0);
+            let scale = if extent > 0.0 { extent / radius } else { 1.0 };
+            Tile {
+                body,
+                rect,
+                center: Point::new(rect.center().x, rect.center().y + 10.0),
+                scale,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::satellite;
+
+    #[test]
+    fn tiles_fit_each_system() {
+        let satellites = vec![
+            satellite(0, MajorBody::Mars, 20000.0),
+            satellite(1, MajorBody::Jupiter, 1000000.0),
+            satellite(2, MajorBody::Jupiter, 500000.0),
+            satellite(3, MajorBody::Saturn, 3000000.0),
+        ];
+        let tiles = layout_tiles(Size::new(400.0, 460.0), satellites.iter());
+        assert_eq!(tiles.len(), 3);
+        assert_eq!(tiles[0].body, MajorBody::Mars);
+        assert_eq!(tiles[2].rect, Rect::new(0.0, 230.0, 200.0, 460.0));
+        // 200 px wide tiles leave a radius of 90 px
+        assert!((tiles[1].scale - 1000000.0 / 90.0).abs() < 1e-6);
+        assert!(tiles.iter().all(|tile| tile.rect.contains(tile.center)));
+    }
+}
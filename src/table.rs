@@ -0,0 +1,517 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use druid::kurbo::{Line, Rect, Vec2};
+use druid::piet::{Color, Text, TextLayoutBuilder};
+use druid::{
+    BoxConstraints, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
+    Point, RenderContext, Size, UpdateCtx, Widget,
+};
+use evalexpr::Value;
+use satellite_data::{database::Database, satellites::Satellite};
+
+use crate::astronomy_canvas::{select, AstronomyCanvasData, SatelliteWrapper};
+use crate::columns::{self, ComputedColumn};
+use crate::fields::{Field, FieldGroup, FIELDS};
+use crate::selection::SelectionMode;
+
+const CHIP_WIDTH: f64 = 75.0;
+const CHIP_ROW_HEIGHT: f64 = 24.0;
+const HEADER_HEIGHT: f64 = 22.0;
+const ROW_HEIGHT: f64 = 20.0;
+
+// a catalogue field or a computed column
+#[derive(Clone)]
+enum Column {
+    Field(&'static Field),
+    Computed(String),
+}
+
+impl Column {
+    fn name(&self) -> &str {
+        match self {
+            Column::Field(field) => field.name,
+            Column::Computed(name) => name,
+        }
+    }
+
+    fn unit(&self) -> &str {
+        match self {
+            Column::Field(field) => field.unit,
+            Column::Computed(_) => "",
+        }
+    }
+
+    fn width(&self) -> f64 {
+        match self {
+            Column::Field(field) if field.group == FieldGroup::Identity => 100.0,
+            _ => 110.0,
+        }
+    }
+
+    fn text(&self, satellite: &Satellite) -> String {
+        match self {
+            Column::Field(field) => field.text(satellite),
+            Column::Computed(name) => columns::text(&columns::value(name, satellite)),
+        }
+    }
+
+    // orders satellite ids, computed values are looked up once per satellite
+    fn sort<'a>(
+        &self,
+        rows: &mut [usize],
+        descending: bool,
+        satellite: impl Fn(usize) -> &'a Satellite,
+    ) {
+        match self {
+            Column::Field(field) => {
+                rows.sort_by(|a, b| compare(field, satellite(*a), satellite(*b), descending))
+            }
+            Column::Computed(name) => {
+                let values: HashMap<usize, Value> = rows
+                    .iter()
+                    .map(|id| (*id, columns::value(name, satellite(*id))))
+                    .collect();
+                rows.sort_by(|a, b| {
+                    let (a, b) = (&values[a], &values[b]);
+                    match (a, b) {
+                        (Value::String(a), Value::String(b)) => {
+                            reverse_if(a.to_lowercase().cmp(&b.to_lowercase()), descending)
+                        }
+                        _ => compare_values(columns::number(a), columns::number(b), descending),
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn reverse_if(ordering: Ordering, descending: bool) -> Ordering {
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+pub fn compare(field: &Field, a: &Satellite, b: &Satellite, descending: bool) -> Ordering {
+    if field.is_numeric() {
+        compare_values(field.number(a), field.number(b), descending)
+    } else {
+        let ordering = field
+            .text(a)
+            .to_lowercase()
+            .cmp(&field.text(b).to_lowercase());
+        reverse_if(ordering, descending)
+    }
+}
+
+// unknown and NaN values sort after all known ones, in either direction
+pub fn compare_values(a: Option<f64>, b: Option<f64>, descending: bool) -> Ordering {
+    let a = a.filter(|value| !value.is_nan());
+    let b = b.filter(|value| !value.is_nan());
+    match (a, b) {
+        (Some(a), Some(b)) => reverse_if(a.partial_cmp(&b).unwrap_or(Ordering::Equal), descending),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// table of the filtered satellites, rows are ids kept in display order
+pub struct SatelliteTable {
+    database: Database,
+    positions: HashMap<usize, usize>,
+    visible: Vec<bool>,
+    computed: Vec<ComputedColumn>,
+    hidden_computed: HashSet<String>,
+    sort: Option<(Column, bool)>,
+    rows: Vec<usize>,
+    offset: Vec2,
+}
+
+impl SatelliteTable {
+    pub fn new(database: Database) -> SatelliteTable {
+        let positions = database
+            .data
+            .iter()
+            .enumerate()
+            .map(|(position, satellite)| (satellite.id, position))
+            .collect();
+        SatelliteTable {
+            database,
+            positions,
+            visible: FIELDS
+                .iter()
+                .map(|field| !matches!(field.group, FieldGroup::Parent | FieldGroup::Derived))
+                .collect(),
+            computed: columns::columns(),
+            hidden_computed: HashSet::new(),
+            sort: None,
+            rows: Vec::new(),
+            offset: Vec2::ZERO,
+        }
+    }
+
+    fn satellite(&self, id: usize) -> Option<&Satellite> {
+        self.positions
+            .get(&id)
+            .map(|position| &self.database.data[*position])
+    }
+
+    fn rebuild_rows(&mut self, data: &AstronomyCanvasData) {
+        self.rows = data
+            .displayed_satellites
+            .0
+            .iter()
+            .map(|wrapper| wrapper.satellite)
+            .filter(|id| self.positions.contains_key(id))
+            .collect();
+        if let Some((column, ascending)) = &self.sort {
+            let database = &self.database;
+            let positions = &self.positions;
+            column.sort(&mut self.rows, !ascending, |id| {
+                &database.data[positions[&id]]
+            });
+        }
+    }
+
+    // every column with its chip label, fields first
+    fn all_columns(&self) -> Vec<Column> {
+        FIELDS
+            .iter()
+            .map(Column::Field)
+            .chain(
+                self.computed
+                    .iter()
+                    .map(|column| Column::Computed(column.name.clone())),
+            )
+            .collect()
+    }
+
+    fn is_visible(&self, index: usize, column: &Column) -> bool {
+        match column {
+            Column::Field(_) => self.visible[index],
+            Column::Computed(name) => !self.hidden_computed.contains(name),
+        }
+    }
+
+    fn visible_columns(&self) -> Vec<(Column, f64)> {
+        let mut x = -self.offset.x;
+        self.all_columns()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, column)| self.is_visible(*index, column))
+            .map(|(_, column)| {
+                let left = x;
+                x += column.width();
+                (column, left)
+            })
+            .collect()
+    }
+
+    fn content_size(&self) -> Size {
+        let width = self
+            .visible_columns()
+            .iter()
+            .map(|(column, _)| column.width())
+            .sum();
+        Size::new(width, self.rows.len() as f64 * ROW_HEIGHT)
+    }
+
+    fn clamp_offset(&mut self, size: Size) {
+        let content = self.content_size();
+        let max_x = (content.width - size.width).max(0.0);
+        let body_height = size.height - self.bar_height(size.width) - HEADER_HEIGHT;
+        let max_y = (content.height - body_height).max(0.0);
+        self.offset.x = self.offset.x.max(0.0).min(max_x);
+        self.offset.y = self.offset.y.max(0.0).min(max_y);
+    }
+
+    // the chips wrap onto as many rows as the width needs, so every column stays reachable
+    fn chips_per_row(width: f64) -> usize {
+        (((width - 5.0) / (CHIP_WIDTH + 3.0)).floor() as usize).max(1)
+    }
+
+    fn chip_rect(index: usize, width: f64) -> Rect {
+        let per_row = SatelliteTable::chips_per_row(width);
+        let x = 5.0 + (index % per_row) as f64 * (CHIP_WIDTH + 3.0);
+        let y = (index / per_row) as f64 * CHIP_ROW_HEIGHT;
+        Rect::new(x, y + 2.0, x + CHIP_WIDTH, y + CHIP_ROW_HEIGHT - 2.0)
+    }
+
+    fn bar_height(&self, width: f64) -> f64 {
+        let per_row = SatelliteTable::chips_per_row(width);
+        let rows = (self.all_columns().len() + per_row - 1) / per_row;
+        rows.max(1) as f64 * CHIP_ROW_HEIGHT
+    }
+
+    fn row_at(&self, y: f64, width: f64) -> Option<usize> {
+        let y = y - self.bar_height(width) - HEADER_HEIGHT + self.offset.y;
+        if y < 0.0 {
+            return None;
+        }
+        let row = (y / ROW_HEIGHT).floor() as usize;
+        self.rows.get(row).copied()
+    }
+
+    fn draw_text(&self, ctx: &mut PaintCtx, text: String, color: Color, origin: Point) {
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .text_color(color)
+            .build()
+            .unwrap();
+        ctx.draw_text(&layout, origin);
+    }
+}
+
+impl Widget<AstronomyCanvasData> for SatelliteTable {
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        match event {
+            Event::Wheel(mouse_event) => {
+                let (x, y) = mouse_event.wheel_delta.into();
+                if mouse_event.mods.shift() {
+                    self.offset.x += y;
+                } else {
+                    self.offset += Vec2::new(x, y);
+                }
+                self.clamp_offset(ctx.size());
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::MouseDown(mouse_event) if mouse_event.button.is_left() => {
+                let pos = mouse_event.pos;
+                let width = ctx.size().width;
+                let bar_height = self.bar_height(width);
+                if pos.y < bar_height {
+                    let columns = self.all_columns();
+                    let clicked = (0..columns.len())
+                        .find(|index| SatelliteTable::chip_rect(*index, width).contains(pos));
+                    if let Some(index) = clicked {
+                        match &columns[index] {
+                            Column::Field(_) => self.visible[index] = !self.visible[index],
+                            Column::Computed(name) => {
+                                if !self.hidden_computed.remove(name) {
+                                    self.hidden_computed.insert(name.clone());
+                                }
+                            }
+                        }
+                        self.clamp_offset(ctx.size());
+                        ctx.request_paint();
+                    }
+                } else if pos.y < bar_height + HEADER_HEIGHT {
+                    let clicked = self
+                        .visible_columns()
+                        .into_iter()
+                        .find(|(column, left)| pos.x >= *left && pos.x < left + column.width())
+                        .map(|(column, _)| column);
+                    if let Some(column) = clicked {
+                        self.sort = match &self.sort {
+                            Some((sorted, ascending)) if sorted.name() == column.name() => {
+                                Some((column, !ascending))
+                            }
+                            _ => Some((column, true)),
+                        };
+                        self.rebuild_rows(data);
+                        ctx.request_paint();
+                    }
+                } else if let Some(id) = self.row_at(pos.y, width) {
+                    let mode = SelectionMode::from_modifiers(mouse_event.mods);
+                    if mode != SelectionMode::Subtract {
+                        data.selected_satellite = Some(SatelliteWrapper {
+                            satellite: id,
+                            selected: true,
+                        });
+                    }
+                    select(data, &[id], mode);
+                    ctx.request_paint();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rebuild_rows(data);
+        }
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &AstronomyCanvasData,
+        data: &AstronomyCanvasData,
+        _env: &Env,
+    ) {
+        if !old_data.columns.same(&data.columns) {
+            self.computed = columns::columns();
+            let defined = |name: &str| self.computed.iter().any(|column| column.name == name);
+            if matches!(&self.sort, Some((Column::Computed(name), _)) if !defined(name)) {
+                self.sort = None;
+            }
+            self.rebuild_rows(data);
+            self.clamp_offset(ctx.size());
+            ctx.request_paint();
+        }
+        if !old_data
+            .displayed_satellites
+            .same(&data.displayed_satellites)
+        {
+            self.rebuild_rows(data);
+            self.clamp_offset(ctx.size());
+            ctx.request_paint();
+        }
+        if !old_data.selected_satellites.same(&data.selected_satellites)
+            || !old_data.selected_satellite.same(&data.selected_satellite)
+        {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _layout_ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AstronomyCanvasData,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AstronomyCanvasData, _env: &Env) {
+        let size = ctx.size();
+        ctx.clip(size.to_rect());
+        ctx.fill(size.to_rect(), &Color::grey(0.1));
+
+        for (index, column) in self.all_columns().iter().enumerate() {
+            let chip = SatelliteTable::chip_rect(index, size.width);
+            let (background, foreground) = if self.is_visible(index, column) {
+                (Color::grey(0.35), Color::WHITE)
+            } else {
+                (Color::grey(0.15), Color::grey(0.5))
+            };
+            ctx.fill(chip, &background);
+            self.draw_text(
+                ctx,
+                column.name().to_string(),
+                foreground,
+                Point::new(chip.x0 + 4.0, chip.y0 + 1.0),
+            );
+        }
+
+        let bar_height = self.bar_height(size.width);
+        let body_top = bar_height + HEADER_HEIGHT;
+        let first_row = (self.offset.y / ROW_HEIGHT).floor() as usize;
+        let row_count = ((size.height - body_top) / ROW_HEIGHT).ceil() as usize + 1;
+        let columns = self.visible_columns();
+        for (row, id) in self.rows.iter().enumerate().skip(first_row).take(row_count) {
+            let satellite = match self.satellite(*id) {
+                Some(satellite) => satellite,
+                None => continue,
+            };
+            let y = body_top + row as f64 * ROW_HEIGHT - self.offset.y;
+            let row_rect = Rect::new(0.0, y, size.width, y + ROW_HEIGHT);
+            let focused = data
+                .selected_satellite
+                .map_or(false, |selected| selected.satellite == *id);
+            let selected = data
+                .selected_satellites
+                .0
+                .iter()
+                .any(|wrapper| wrapper.satellite == *id);
+            if focused {
+                ctx.fill(row_rect, &Color::AQUA.with_alpha(0.4));
+            } else if selected {
+                ctx.fill(row_rect, &Color::AQUA.with_alpha(0.2));
+            } else if row % 2 == 1 {
+                ctx.fill(row_rect, &Color::grey(0.13));
+            }
+            for (column, left) in &columns {
+                if *left > size.width || left + column.width() < 0.0 {
+                    continue;
+                }
+                self.draw_text(
+                    ctx,
+                    column.text(satellite),
+                    Color::WHITE,
+                    Point::new(left + 4.0, y + 2.0),
+                );
+            }
+        }
+
+        let header = Rect::new(0.0, bar_height, size.width, body_top);
+        ctx.fill(header, &Color::grey(0.25));
+        for (column, left) in &columns {
+            let mut title = column.name().to_string();
+            if !column.unit().is_empty() {
+                title.push_str(&format!(" [{}]", column.unit()));
+            }
+            if let Some((sorted, ascending)) = &self.sort {
+                if sorted.name() == column.name() {
+                    title.push_str(if ascending { " ▲" } else { " ▼" });
+                }
+            }
+            self.draw_text(
+                ctx,
+                title,
+                Color::WHITE,
+                Point::new(left + 4.0, bar_height + 2.0),
+            );
+            let separator = Line::new((*left, bar_height), (*left, size.height));
+            ctx.stroke(separator, &Color::grey(0.3), 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_values_sort_last() {
+        let mut values = vec![Some(3.0), None, Some(f64::NAN), Some(-1.0), Some(2.0)];
+        values.sort_by(|a, b| compare_values(*a, *b, false));
+        assert_eq!(values[..3], [Some(-1.0), Some(2.0), Some(3.0)]);
+        assert!(values[3..]
+            .iter()
+            .all(|value| value.map_or(true, |value| value.is_nan())));
+        values.sort_by(|a, b| compare_values(*a, *b, true));
+        assert_eq!(values[..3], [Some(3.0), Some(2.0), Some(-1.0)]);
+        assert!(values[3..]
+            .iter()
+            .all(|value| value.map_or(true, |value| value.is_nan())));
+    }
+
+    #[test]
+    fn chips_wrap_within_the_width() {
+        let width = 400.0;
+        let chips: Vec<Rect> = (0..FIELDS.len())
+            .map(|index| SatelliteTable::chip_rect(index, width))
+            .collect();
+        assert!(chips.iter().all(|chip| chip.x0 >= 0.0 && chip.x1 <= width));
+        assert!(chips.last().unwrap().y0 > chips[0].y0);
+        for (index, chip) in chips.iter().enumerate() {
+            assert!(chips[index + 1..]
+                .iter()
+                .all(|other| chip.intersect(*other).area() == 0.0));
+        }
+        assert_eq!(
+            SatelliteTable::chip_rect(3, 10.0).y0,
+            3.0 * CHIP_ROW_HEIGHT + 2.0
+        );
+    }
+}
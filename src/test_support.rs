@@ -0,0 +1,89 @@
+use satellite_data::data::astronomical_data::{OrbitalParams, PhysicalParams};
+use satellite_data::data::data_with_error::DataWithError;
+use satellite_data::satellites::{MajorBody, Satellite};
+
+// a physical parameter the catalogue marks as unknown
+pub const UNKNOWN: (f64, f64) = (0.0, -1.0);
+
+// a satellite named "S/<id>" on a circular equatorial orbit, with every physical
+// parameter 1.0 without an uncertainty; the setters below change the rest
+pub fn satellite(id: usize, major_body: MajorBody, major_semiaxis: f64) -> Satellite {
+    Satellite {
+        major_body,
+        name: format!("S/{}", id),
+        id,
+        orbital_params: OrbitalParams {
+            major_semiaxis,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+        },
+        physical_params: PhysicalParams::new(
+            1.0.into(),
+            1.0.into(),
+            1.0.into(),
+            1.0.into(),
+            1.0.into(),
+        ),
+    }
+}
+
+// physical parameters take a value, or a (value, error) pair such as `UNKNOWN`
+pub trait SatelliteBuilder {
+    fn named(self, name: &str) -> Self;
+    fn eccentricity(self, eccentricity: f64) -> Self;
+    fn inclination(self, inclination: f64) -> Self;
+    fn ascending_node(self, ascending_node: f64) -> Self;
+    fn gm(self, gm: impl Into<DataWithError<f64>>) -> Self;
+    fn radius(self, radius: impl Into<DataWithError<f64>>) -> Self;
+    fn density(self, density: impl Into<DataWithError<f64>>) -> Self;
+    fn magnitude(self, magnitude: impl Into<DataWithError<f64>>) -> Self;
+    fn albedo(self, albedo: impl Into<DataWithError<f64>>) -> Self;
+}
+
+impl SatelliteBuilder for Satellite {
+    fn named(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    fn eccentricity(mut self, eccentricity: f64) -> Self {
+        self.orbital_params.eccentricity = eccentricity;
+        self
+    }
+
+    fn inclination(mut self, inclination: f64) -> Self {
+        self.orbital_params.inclination = inclination;
+        self
+    }
+
+    fn ascending_node(mut self, ascending_node: f64) -> Self {
+        self.orbital_params.ascending_node = ascending_node;
+        self
+    }
+
+    fn gm(mut self, gm: impl Into<DataWithError<f64>>) -> Self {
+        self.physical_params.gm = gm.into();
+        self
+    }
+
+    fn radius(mut self, radius: impl Into<DataWithError<f64>>) -> Self {
+        self.physical_params.radius = radius.into();
+        self
+    }
+
+    fn density(mut self, density: impl Into<DataWithError<f64>>) -> Self {
+        self.physical_params.density = density.into();
+        self
+    }
+
+    fn magnitude(mut self, magnitude: impl Into<DataWithError<f64>>) -> Self {
+        self.physical_params.magnitude = magnitude.into();
+        self
+    }
+
+    fn albedo(mut self, albedo: impl Into<DataWithError<f64>>) -> Self {
+        self.physical_params.albedo = albedo.into();
+        self
+    }
+}